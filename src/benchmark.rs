@@ -0,0 +1,134 @@
+use std::time::Duration;
+use clap::ValueEnum;
+use color_eyre::eyre::Result;
+use serde::Deserialize;
+use crate::results::{BenchmarkResult, Parameters, Unit};
+
+///Benchmarks which can be selected with `--only` and `--skip`
+#[derive(ValueEnum, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum BenchmarkKind {
+    ///Single PI calculation on one core
+    Cpu,
+    ///Parallel PI calculations on all cores
+    CpuMulti,
+    ///Parallel PI calculations on 1, 2, 4 ... up to all cores. Only runs when selected with --only
+    CpuScaling,
+    ///Sequential write and read of a large file
+    Disk,
+    ///Random block sized reads and writes
+    DiskRandom,
+    ///Small writes each followed by a flush to stable storage
+    DiskSync,
+    ///STREAM memory bandwidth kernels on one thread
+    Memory,
+    ///STREAM memory bandwidth kernels on all cores
+    MemoryMulti,
+    ///Pointer chasing latency across growing working sets
+    MemoryLatency,
+}
+
+impl BenchmarkKind {
+    ///Category of the benchmark in the `Registry`, the name accepted by `--only` and `--skip`, e.g. `cpu-multi`
+    pub fn name(&self) -> String {
+        self.to_possible_value().expect("Every benchmark kind can be selected").get_name().to_string()
+    }
+
+    ///Built-in benchmark of the category `name`
+    pub fn from_name(name: &str) -> Option<Self> {
+        <Self as ValueEnum>::from_str(name, false).ok()
+    }
+
+    ///Whether the benchmark runs when no benchmarks are selected explicitly.
+    ///The CPU scaling curve repeats the multi-core test once per thread count, so it is opt-in.
+    pub fn is_default(&self) -> bool {
+        *self != BenchmarkKind::CpuScaling
+    }
+}
+
+///Outcome of one iteration of a benchmark
+#[derive(Debug, Clone, PartialEq)]
+pub struct Measurement {
+    ///Wall-clock duration of the iteration
+    pub elapsed: Duration,
+    ///Value of the iteration in the unit of the benchmark
+    pub value: f64,
+    ///Latency of every individual operation, for benchmarks issuing many small requests
+    pub latencies: Vec<Duration>,
+}
+
+impl Measurement {
+    ///Time taken by the iteration in seconds
+    pub fn time(elapsed: Duration) -> Self {
+        Self { elapsed, value: elapsed.as_secs_f64(), latencies: Vec::new() }
+    }
+
+    ///Time taken by one of the `operations` done in the iteration, in seconds
+    pub fn time_per_operation(elapsed: Duration, operations: usize) -> Self {
+        Self { elapsed, value: elapsed.as_secs_f64() / operations.max(1) as f64, latencies: Vec::new() }
+    }
+
+    ///Units of `work` done per second, e.g. bytes or operations
+    pub fn throughput(work: f64, elapsed: Duration) -> Self {
+        Self { elapsed, value: work / elapsed.as_secs_f64().max(f64::MIN_POSITIVE), latencies: Vec::new() }
+    }
+
+    pub fn with_latencies(mut self, latencies: Vec<Duration>) -> Self {
+        self.latencies = latencies;
+        self
+    }
+}
+
+///Workload measured by repeating `one_iteration`. Every benchmark produces one result,
+///iterations, warm-up, progress, statistics and reporting are handled by the `Registry`.
+pub trait Benchmark {
+    ///Name of the result, e.g. `disk_write`
+    fn name(&self) -> String;
+
+    ///Group the benchmark runs in and is selected by, e.g. `cpu-multi`. Built-in benchmarks use
+    ///`BenchmarkKind::name`, other workloads their own name so they can be selected on their own.
+    fn category(&self) -> String;
+
+    fn parameters(&self) -> Parameters;
+
+    fn unit(&self) -> Unit;
+
+    ///Printed in front of the average, e.g. `Write took`
+    fn description(&self) -> String;
+
+    ///Shown while the benchmark runs, followed by the number of iterations
+    fn progress_message(&self) -> String;
+
+    ///Prepares everything the iterations need, e.g. buffers or a benchmark file. Not timed
+    fn setup(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn one_iteration(&mut self) -> Result<Measurement>;
+
+    ///Releases what `setup` prepared. Also called when `setup` or an iteration failed
+    fn teardown(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    ///Adds values only this benchmark reports to its result.
+    ///`previous` holds the results of the same category completed before this one.
+    fn finish(&self, result: BenchmarkResult, _previous: &[BenchmarkResult]) -> BenchmarkResult {
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn category_names_match_the_command_line() {
+        assert_eq!(BenchmarkKind::CpuMulti.name(), "cpu-multi");
+        assert_eq!(BenchmarkKind::DiskRandom.name(), "disk-random");
+        for kind in BenchmarkKind::value_variants() {
+            assert_eq!(BenchmarkKind::from_name(&kind.name()), Some(*kind));
+        }
+        assert_eq!(BenchmarkKind::from_name("my-workload"), None);
+    }
+}
@@ -0,0 +1,244 @@
+use std::fmt;
+use color_eyre::eyre::Result;
+use console::Style;
+use crate::results::{BenchmarkReport, BenchmarkResult};
+
+///Exit status of a run which regressed beyond `--max-regression` against `--baseline`, or missed a benchmark of it
+pub const REGRESSION_EXIT_CODE: u8 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Faster,
+    Slower,
+    Similar,
+}
+
+impl Verdict {
+    pub fn style(&self) -> Style {
+        match self {
+            Verdict::Faster => Style::new().bright().green().bold(),
+            Verdict::Slower => Style::new().bright().red().bold(),
+            Verdict::Similar => Style::new().bright().yellow().bold(),
+        }
+    }
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Verdict::Faster => write!(f, "faster"),
+            Verdict::Slower => write!(f, "slower"),
+            Verdict::Similar => write!(f, "similar"),
+        }
+    }
+}
+
+///Relative performance of one benchmark between two runs
+pub struct Comparison<'a> {
+    pub baseline: &'a BenchmarkResult,
+    pub candidate: &'a BenchmarkResult,
+    ///How many times faster the candidate is than the baseline, e.g. 0.5 means twice as slow
+    pub ratio: f64,
+}
+
+impl Comparison<'_> {
+    pub fn delta_percent(&self) -> f64 {
+        (self.ratio - 1.0) * 100.0
+    }
+
+    pub fn within_noise(&self) -> bool {
+        self.baseline.statistics.overlaps(&self.candidate.statistics)
+    }
+
+    pub fn verdict(&self, threshold_percent: f64) -> Verdict {
+        let delta = self.delta_percent();
+        if delta > threshold_percent {
+            Verdict::Faster
+        } else if delta < -threshold_percent {
+            Verdict::Slower
+        } else {
+            Verdict::Similar
+        }
+    }
+}
+
+pub struct RunComparison {
+    baseline: BenchmarkReport,
+    candidate: BenchmarkReport,
+    threshold_percent: f64,
+}
+
+impl RunComparison {
+    pub fn new(baseline_file: &str, candidate_file: &str, threshold_percent: f64) -> Result<Self> {
+        Ok(Self::from_reports(BenchmarkReport::load(baseline_file)?,
+                              BenchmarkReport::load(candidate_file)?,
+                              threshold_percent))
+    }
+
+    pub fn from_reports(baseline: BenchmarkReport, candidate: BenchmarkReport, threshold_percent: f64) -> Self {
+        Self { baseline, candidate, threshold_percent }
+    }
+
+    ///Pairs every baseline result with the candidate result of the same benchmark and workload
+    pub fn comparisons(&self) -> Vec<Comparison<'_>> {
+        self.baseline.results.iter()
+            .filter_map(|baseline| {
+                self.candidate.results.iter()
+                    .find(|candidate| Self::comparable(baseline, candidate))
+                    .map(|candidate| Comparison {
+                        baseline,
+                        candidate,
+                        ratio: Self::ratio(baseline, candidate),
+                    })
+            })
+            .collect()
+    }
+
+    ///Comparisons in which the candidate is slower than the baseline by more than the threshold
+    pub fn regressions(&self) -> Vec<Comparison<'_>> {
+        self.comparisons()
+            .into_iter()
+            .filter(|comparison| comparison.verdict(self.threshold_percent) == Verdict::Slower)
+            .collect()
+    }
+
+    ///Baseline results without a candidate result of the same workload, e.g. because the benchmark was skipped
+    pub fn missing(&self) -> Vec<&BenchmarkResult> {
+        Self::unmatched(&self.baseline.results, &self.candidate.results)
+    }
+
+    ///Lists the regressions on standard error, so they never mix with JSON printed to standard output
+    pub fn print_regressions(&self, baseline_file: &str) {
+        let regressions = self.regressions();
+        if regressions.is_empty() {
+            eprintln!("No benchmark regressed by more than {}% against {}.", self.threshold_percent, baseline_file);
+            return;
+        }
+
+        eprintln!("{} benchmark{} regressed by more than {}% against {}:",
+                  regressions.len(),
+                  if regressions.len() == 1 { "" } else { "s" },
+                  self.threshold_percent,
+                  baseline_file);
+        for comparison in regressions {
+            eprintln!("    {}", comparison.baseline.label());
+            eprintln!("        {:<22}{}", "Baseline:", comparison.baseline.unit.format(comparison.baseline.average));
+            eprintln!("        {:<22}{}", "Candidate:", comparison.candidate.unit.format(comparison.candidate.average));
+            eprintln!("        {:<22}{}",
+                      "Result:",
+                      Verdict::Slower.style().apply_to(format!("{:.2}x ({:+.1}%)", comparison.ratio, comparison.delta_percent())));
+        }
+    }
+
+    ///Lists the baseline benchmarks this run has no result for on standard error
+    pub fn print_missing(&self, baseline_file: &str) {
+        let missing = self.missing();
+        if missing.is_empty() {
+            return;
+        }
+
+        eprintln!("{} benchmark{} of {} did not run or ran with other parameters:",
+                  missing.len(),
+                  if missing.len() == 1 { "" } else { "s" },
+                  baseline_file);
+        for result in missing {
+            eprintln!("    {}", result.label());
+        }
+    }
+
+    ///How many times faster `candidate` is than `baseline`
+    pub fn ratio(baseline: &BenchmarkResult, candidate: &BenchmarkResult) -> f64 {
+        if baseline.unit.higher_is_better() {
+            candidate.average / baseline.average
+        } else {
+            baseline.average / candidate.average
+        }
+    }
+
+    ///Whether both results are of the same workload and have measurements, results of failed benchmarks may have none
+    fn comparable(result: &BenchmarkResult, other: &BenchmarkResult) -> bool {
+        result.matches(other) && !result.measurements.is_empty() && !other.measurements.is_empty()
+    }
+
+    fn unmatched<'a>(results: &'a [BenchmarkResult], others: &[BenchmarkResult]) -> Vec<&'a BenchmarkResult> {
+        results.iter()
+            .filter(|result| !others.iter().any(|other| Self::comparable(result, other)))
+            .collect()
+    }
+
+    pub fn print(&self) {
+        let system_info_style = Style::new().bright().green().bold();
+        println!("{:<30}{:<10}", "Baseline system:", system_info_style.apply_to(&self.baseline.system.os_version));
+        println!("{:<30}{:<10}", "Candidate system:", system_info_style.apply_to(&self.candidate.system.os_version));
+        println!();
+
+        for comparison in self.comparisons() {
+            let verdict = comparison.verdict(self.threshold_percent);
+            println!("{}", comparison.baseline.label());
+            println!("    {:<26}{}", "Baseline:", comparison.baseline.unit.format(comparison.baseline.average));
+            println!("    {:<26}{}", "Candidate:", comparison.candidate.unit.format(comparison.candidate.average));
+            println!("    {:<26}{}",
+                     "Result:",
+                     verdict.style().apply_to(format!("{:.2}x ({:+.1}%) {}", comparison.ratio, comparison.delta_percent(), verdict)));
+            if comparison.within_noise() {
+                println!("    {:<26}95% confidence intervals overlap, the difference may be noise", "Note:");
+            }
+            println!();
+        }
+
+        for result in Self::unmatched(&self.baseline.results, &self.candidate.results) {
+            println!("{} only exists in the baseline.", result.label());
+        }
+        for result in Self::unmatched(&self.candidate.results, &self.baseline.results) {
+            println!("{} only exists in the candidate.", result.label());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use super::*;
+    use crate::results::{Parameters, SystemInfo, Unit};
+
+    fn result(name: &str, parameters: &[(&str, serde_json::Value)], average: f64) -> BenchmarkResult {
+        let parameters = parameters.iter()
+            .map(|(key, value)| (key.to_string(), value.clone()))
+            .collect::<Parameters>();
+        BenchmarkResult::new(name, String::new(), parameters, Unit::CalculationsPerSecond, vec![average])
+    }
+
+    fn report(results: Vec<BenchmarkResult>) -> BenchmarkReport {
+        let mut report = BenchmarkReport::new(SystemInfo::collect());
+        results.into_iter().for_each(|result| report.add_result(result));
+        report
+    }
+
+    #[test]
+    fn pairs_results_of_machines_with_other_core_counts() {
+        let baseline = report(vec![result("cpu_multi", &[("precision", json!(3000)), ("threads", json!(8))], 100.0)]);
+        let candidate = report(vec![result("cpu_multi", &[("precision", json!(3000)), ("threads", json!(1))], 1.0)]);
+        let comparison = RunComparison::from_reports(baseline, candidate, 5.0);
+
+        assert_eq!(comparison.regressions().len(), 1);
+        assert!(comparison.missing().is_empty());
+    }
+
+    #[test]
+    fn does_not_pair_other_workloads() {
+        let baseline = report(vec![
+            result("cpu_multi", &[("precision", json!(3000))], 1.0),
+            result("cpu_scaling", &[("precision", json!(3000)), ("threads", json!(8))], 1.0),
+            result("memory_copy", &[("array_size", json!(1024))], 1.0),
+        ]);
+        let candidate = report(vec![
+            result("cpu_multi", &[("precision", json!(1000))], 1.0),
+            result("cpu_scaling", &[("precision", json!(3000)), ("threads", json!(1))], 1.0),
+        ]);
+        let comparison = RunComparison::from_reports(baseline, candidate, 5.0);
+
+        assert!(comparison.comparisons().is_empty());
+        let missing: Vec<&str> = comparison.missing().iter().map(|result| result.name.as_str()).collect();
+        assert_eq!(missing, ["cpu_multi", "cpu_scaling", "memory_copy"]);
+    }
+}
@@ -0,0 +1,203 @@
+use std::fs;
+use clap::parser::ValueSource;
+use clap::{ArgMatches, ValueEnum};
+use color_eyre::eyre::{Result, WrapErr};
+use color_eyre::Section;
+use serde::Deserialize;
+use env_benchmark::sync_disk_benchmark::SyncMethod;
+use env_benchmark::BenchmarkKind;
+use crate::Args;
+
+///Built-in configurations selected with `--profile`
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Profile {
+    ///Smoke test finishing in about a minute, with small files and arrays and no CPU scaling curve
+    Quick,
+    ///The default arguments, the results can be scored against the built-in reference machine
+    Standard,
+    ///Twice the iterations of the standard profile on larger files and arrays for nightly runs, taking about twice as long
+    Thorough,
+}
+
+impl Profile {
+    fn config(&self) -> &'static str {
+        match self {
+            Profile::Quick => include_str!("profiles/quick.toml"),
+            Profile::Standard => include_str!("profiles/standard.toml"),
+            Profile::Thorough => include_str!("profiles/thorough.toml"),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+struct CpuConfig {
+    precision: Option<u32>,
+    num_calculations: Option<u32>,
+    threads: Option<u32>,
+    cpu_list: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+struct MemoryConfig {
+    array_size: Option<String>,
+    latency_max_size: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+struct DiskConfig {
+    file_size: Option<String>,
+    buffer_size: Option<String>,
+    sync_write: Option<bool>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+struct RandomDiskConfig {
+    file_size: Option<String>,
+    block_size: Option<String>,
+    read_percent: Option<u32>,
+    queue_depth: Option<u32>,
+    operations: Option<u32>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+struct SyncDiskConfig {
+    method: Option<SyncMethod>,
+    record_size: Option<String>,
+    operations: Option<u32>,
+}
+
+///Benchmark suite read from a TOML file. Every key is optional and falls back to the command line default,
+///arguments given on the command line take precedence over the file. See `src/profiles` for examples.
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    ///Benchmarks to run, like `--only`
+    benchmarks: Option<Vec<BenchmarkKind>>,
+    ///Benchmarks to skip, like `--skip`
+    skip: Option<Vec<BenchmarkKind>>,
+    iterations: Option<u32>,
+    warmup: Option<u32>,
+    duration: Option<u32>,
+    min_iterations: Option<u32>,
+    temp_file_directory: Option<String>,
+    cpu: CpuConfig,
+    memory: MemoryConfig,
+    disk: DiskConfig,
+    disk_random: RandomDiskConfig,
+    disk_sync: SyncDiskConfig,
+}
+
+impl Config {
+    pub fn load(path: &str) -> Result<Self> {
+        let content = fs::read_to_string(path).wrap_err_with(|| format!("Unable to read the configuration file {}", path))?;
+        toml::from_str(&content)
+            .wrap_err_with(|| format!("{} is not a valid configuration file", path))
+            .suggestion("Sizes are strings such as \"4GB\", benchmarks use the names accepted by --only")
+    }
+
+    pub fn profile(profile: Profile) -> Self {
+        toml::from_str(profile.config()).expect("The built-in profiles are valid")
+    }
+
+    ///Overrides the arguments in `args` which were not given on the command line
+    pub fn apply(self, args: &mut Args, matches: &ArgMatches) {
+        let set = |id: &str| matches.value_source(id) != Some(ValueSource::CommandLine);
+
+        Self::set(set("only"), &mut args.only, self.benchmarks);
+        Self::set(set("skip"), &mut args.skip, self.skip);
+        Self::set(set("iterations"), &mut args.iterations, self.iterations);
+        Self::set(set("warmup"), &mut args.warmup, self.warmup);
+        Self::set(set("duration"), &mut args.duration, self.duration.map(Some));
+        Self::set(set("min_iterations"), &mut args.min_iterations, self.min_iterations);
+        Self::set(set("temp_file_directory"), &mut args.temp_file_directory, self.temp_file_directory);
+
+        Self::set(set("pi_precision"), &mut args.pi_precision, self.cpu.precision);
+        Self::set(set("num_calculations"), &mut args.num_calculations, self.cpu.num_calculations);
+        Self::set(set("threads"), &mut args.threads, self.cpu.threads.map(Some));
+        Self::set(set("cpu_list"), &mut args.cpu_list, self.cpu.cpu_list.map(Some));
+
+        Self::set(set("memory_array_size"), &mut args.memory_array_size, self.memory.array_size);
+        Self::set(set("latency_max_size"), &mut args.latency_max_size, self.memory.latency_max_size);
+
+        Self::set(set("filesize"), &mut args.filesize, self.disk.file_size);
+        Self::set(set("buffer_size"), &mut args.buffer_size, self.disk.buffer_size);
+        Self::set(set("sync_write"), &mut args.sync_write, self.disk.sync_write);
+
+        Self::set(set("random_filesize"), &mut args.random_filesize, self.disk_random.file_size);
+        Self::set(set("block_size"), &mut args.block_size, self.disk_random.block_size);
+        Self::set(set("read_percent"), &mut args.read_percent, self.disk_random.read_percent);
+        Self::set(set("queue_depth"), &mut args.queue_depth, self.disk_random.queue_depth);
+        Self::set(set("random_operations"), &mut args.random_operations, self.disk_random.operations);
+
+        Self::set(set("sync_method"), &mut args.sync_method, self.disk_sync.method);
+        Self::set(set("sync_record_size"), &mut args.sync_record_size, self.disk_sync.record_size);
+        Self::set(set("sync_operations"), &mut args.sync_operations, self.disk_sync.operations);
+    }
+
+    fn set<T>(overridable: bool, argument: &mut T, value: Option<T>) {
+        if let (true, Some(value)) = (overridable, value) {
+            *argument = value;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::{CommandFactory, FromArgMatches};
+    use super::*;
+
+    fn parse(command_line: &[&str]) -> (Args, ArgMatches) {
+        let matches = Args::command().get_matches_from(["env_benchmark"].iter().chain(command_line));
+        (Args::from_arg_matches(&matches).unwrap(), matches)
+    }
+
+    fn config(content: &str) -> Config {
+        toml::from_str(content).unwrap()
+    }
+
+    #[test]
+    fn builtin_profiles_are_valid() {
+        for profile in Profile::value_variants() {
+            Config::profile(*profile);
+        }
+    }
+
+    #[test]
+    fn profile_overrides_the_defaults() {
+        let (mut args, matches) = parse(&[]);
+        Config::profile(Profile::Quick).apply(&mut args, &matches);
+        assert_eq!(args.iterations, 2);
+        assert_eq!(args.pi_precision, 1000);
+        assert_eq!(args.filesize, "256MB");
+        assert_eq!(args.queue_depth, 32);
+    }
+
+    #[test]
+    fn config_overrides_the_profile() {
+        let (mut args, matches) = parse(&[]);
+        Config::profile(Profile::Quick).apply(&mut args, &matches);
+        config("iterations = 3\nduration = 60\n[cpu]\nthreads = 2\n").apply(&mut args, &matches);
+        assert_eq!(args.iterations, 3);
+        assert_eq!(args.duration, Some(60));
+        assert_eq!(args.threads, Some(2));
+        assert_eq!(args.pi_precision, 1000);
+    }
+
+    #[test]
+    fn command_line_overrides_the_profile_and_config() {
+        let (mut args, matches) = parse(&["--iterations", "7", "-p", "2000", "--only", "memory", "--sync-write"]);
+        Config::profile(Profile::Thorough).apply(&mut args, &matches);
+        config("iterations = 3\nbenchmarks = [\"cpu\"]\n[cpu]\nprecision = 500\n[disk]\nsync_write = false\n")
+            .apply(&mut args, &matches);
+        assert_eq!(args.iterations, 7);
+        assert_eq!(args.pi_precision, 2000);
+        assert_eq!(args.only, [BenchmarkKind::Memory]);
+        assert!(args.sync_write);
+        assert_eq!(args.warmup, 2);
+    }
+}
@@ -1,55 +1,77 @@
-use std::hint::black_box;
-use std::sync::{Arc, Mutex};
-use std::sync::atomic::{AtomicI32, AtomicU32, Ordering};
-use std::{io, thread};
-use std::io::{stdout, Write};
 use std::time::{Duration, Instant};
-use console::Style;
-use indicatif::{HumanCount, HumanDuration, HumanFloatCount, ProgressBar, ProgressStyle};
-use color_eyre::eyre::{Report, Result};
-use dashu::base::SquareRoot;
+use color_eyre::eyre::{eyre, Report, Result};
+use dashu::base::{Approximation, SquareRoot};
 use dashu::float::FBig;
 use dashu::integer::IBig;
 use rayon::iter::IntoParallelRefIterator;
 use rayon::iter::ParallelIterator;
-use sysinfo::System;
+use rayon::ThreadPool;
+use serde_json::json;
+use crate::benchmark::{Benchmark, BenchmarkKind, Measurement};
+use crate::cpu_thread_pool::CPUThreadPool;
+use crate::interrupt::Interrupt;
+use crate::pi_verification::PiVerification;
+use crate::results::{BenchmarkResult, Parameters, Unit};
 
 pub struct CPUBenchmark {
+    category: BenchmarkKind,
     precision: usize,
-    num_iterations: u32,
     num_calculations: u32,
     calculation_queue: Vec<u32>,
+    verification: PiVerification,
+    thread_pool: CPUThreadPool,
+    threads: usize,
+    ///Report calculations per second instead of seconds,
+    ///for time bounded runs which repeat the calculations a varying number of times
+    throughput: bool,
+    pool: Option<ThreadPool>,
+    interrupt: Interrupt,
 }
 
 impl CPUBenchmark {
-    pub fn new(precision: usize,
-               num_iterations: u32,
-               num_calculations: u32) -> CPUBenchmark {
+    pub fn new(category: BenchmarkKind,
+               precision: usize,
+               num_calculations: u32,
+               thread_pool: CPUThreadPool,
+               threads: usize,
+               throughput: bool) -> CPUBenchmark {
         Self
         {
-            precision, 
-            num_iterations, 
+            category,
+            precision,
             num_calculations,
-            calculation_queue: (0..num_calculations).collect()
+            calculation_queue: (0..num_calculations).collect(),
+            verification: PiVerification::new(precision),
+            thread_pool,
+            threads,
+            throughput,
+            pool: None,
+            interrupt: Interrupt::new(),
         }
     }
 
+    ///Stops the calculations early once `interrupt` is set
+    pub fn with_interrupt(mut self, interrupt: Interrupt) -> Self {
+        self.interrupt = interrupt;
+        self
+    }
+
     fn binary_split(a: u32, b: u32) -> (IBig, IBig, IBig) {
         if b - a == 1 {
             if a == 0 {
                 let pab = IBig::from(1);
                 let qab = IBig::from(1);
-                let rab = IBig::from(&pab * (13_591_409 + 545_140_134 * a));
+                let rab = &pab * (13_591_409 + 545_140_134 * a);
                 return (pab, qab, rab);
             }
             let a_bigint = IBig::from(a);
-            let pab: IBig = (IBig::from(6 * &a_bigint) - 5)
-                * (IBig::from(2 * &a_bigint) - 1)
-                * (IBig::from(6 * &a_bigint) - 1);
+            let pab: IBig = (6 * &a_bigint - 5)
+                * (2 * &a_bigint - 1)
+                * (6 * &a_bigint - 1);
             let qab = a_bigint.clone().pow(3) * 10_939_058_860_032_000u64;
             let rab = &pab * (13_591_409 + 545_140_134 * a_bigint);
 
-            if a % 2 == 0 {
+            if a.is_multiple_of(2) {
                 return (pab, qab, rab);
             }
             return (pab, qab, -1 * rab);
@@ -57,9 +79,9 @@ impl CPUBenchmark {
         let m = (a + b) / 2;
         let (pam, qam, ram) = Self::binary_split(a, m);
         let (pmb, qmb, rmb) = Self::binary_split(m, b);
-        let p1n = IBig::from(&pam * &pmb);
-        let q1n = IBig::from(&qam * &qmb);
-        let r1n = IBig::from(&ram * &qmb) + IBig::from(&pam * &rmb);
+        let p1n = &pam * &pmb;
+        let q1n = &qam * &qmb;
+        let r1n = &ram * &qmb + &pam * &rmb;
         (p1n, q1n, r1n)
     }
 
@@ -80,56 +102,100 @@ impl CPUBenchmark {
                 }
             }
         }
-        let used_precision = digits * 4;
+        // Short precisions still need enough bits to hold the constants of the series exactly
+        let used_precision = (digits * 4).max(53);
         let digits_per_term = f32::log10(10_939_058_860_032_000_f32 / 6f32 / 2f32 / 6f32);
         let n = (digits as f32 / digits_per_term).ceil() as u32;
         let i1 = IBig::from(426_880);
-        let i2 = FBig::try_from( 10_005).unwrap().with_precision(used_precision).unwrap();
+        let Approximation::Exact(i2) = FBig::from(10_005).with_precision(used_precision) else {
+            return Err(eyre!("Unable to hold 10005 in {} bits of precision", used_precision));
+        };
 
         let (_, q1n, r1n) = Self::binary_split(0, n);
-        Ok(((i1 * i2.sqrt() * q1n) / r1n).into())
+        Ok((i1 * i2.sqrt() * q1n) / r1n)
     }
 
-    pub fn one_iteration(self: Arc<Self>) -> u128 {
+    ///Time taken by all calculations on the thread pool built by `setup`
+    pub fn calculate(&self) -> Result<Duration> {
+        let pool = self.pool.as_ref().expect("The thread pool is built by setup");
         let now = Instant::now();
 
-        self.calculation_queue.par_iter().for_each(|i|{
-            Self::chudnovsky(self.precision).unwrap().to_decimal().value();
-        });
-
-        now.elapsed().as_millis()
-    }
-
-    pub fn run(self: Arc<Self>){
-        let value_style = Style::new().bright().green().bold().underlined();
-        let bar = ProgressBar::new(self.num_iterations as u64)
-            .with_message(format!("Running {} PI calculations with precision {} for {} times",
-                                  self.num_calculations,
-                                  self.precision,
-                                  self.num_iterations));
-        bar.set_style(ProgressStyle::with_template("{msg} [{elapsed}]\n{wide_bar:.cyan/blue} {pos}/{len}")
-            .unwrap()
-            .progress_chars("##-"));
-        bar.enable_steady_tick(Duration::from_secs(1));
-        bar.inc(0);
-
-        let mut measurements = vec![0; self.num_iterations as usize];
-
-        for i in 0..self.num_iterations {
-            let s = self.clone();
-            let time_taken = s.one_iteration();
-            //println!("Iteration {} took {}s", i, Duration::from_millis(time_taken as u64).as_secs());
-            measurements.push(time_taken);
-            bar.inc(1);
+        pool.install(|| self.calculation_queue.par_iter().try_for_each(|_|{
+            if self.interrupt.is_set() {
+                return Ok(());
+            }
+            let pi = Self::chudnovsky(self.precision)?.to_decimal().value();
+            self.verification.verify(&pi.to_string())
+        }))?;
+
+        Ok(now.elapsed())
+    }
+}
+
+impl Benchmark for CPUBenchmark {
+    fn name(&self) -> String {
+        String::from(if self.num_calculations == 1 { "cpu_single" } else { "cpu_multi" })
+    }
+
+    fn category(&self) -> String {
+        self.category.name()
+    }
+
+    fn parameters(&self) -> Parameters {
+        Parameters::from([
+            (String::from("precision"), json!(self.precision)),
+            (String::from("num_calculations"), json!(self.num_calculations)),
+            (String::from("threads"), json!(self.threads.min(self.num_calculations as usize))),
+        ])
+    }
+
+    fn unit(&self) -> Unit {
+        if self.throughput { Unit::CalculationsPerSecond } else { Unit::Seconds }
+    }
+
+    fn description(&self) -> String {
+        format!("{} PI calculations with precision {} {}",
+                self.num_calculations,
+                self.precision,
+                if self.throughput { "reached" } else { "took" })
+    }
+
+    fn progress_message(&self) -> String {
+        format!("Running {} PI calculations with precision {}", self.num_calculations, self.precision)
+    }
+
+    fn setup(&mut self) -> Result<()> {
+        self.pool = Some(self.thread_pool.build(self.threads)?);
+        Ok(())
+    }
+
+    fn one_iteration(&mut self) -> Result<Measurement> {
+        let elapsed = self.calculate()?;
+        if self.throughput {
+            return Ok(Measurement::throughput(self.num_calculations as f64, elapsed));
         }
-        bar.finish();
-        let average= measurements.iter().sum::<u128>() / self.num_iterations as u128;
+        Ok(Measurement::time(elapsed))
+    }
+
+    fn teardown(&mut self) -> Result<()> {
+        self.pool = None;
+        Ok(())
+    }
 
-        println!("{} PI calculations with precision {} took {} on average.",
-                 self.num_calculations,
-                 self.precision,
-                 value_style
-                     .apply_to(HumanDuration(Duration::from_millis(average as u64))));
+    fn finish(&self, result: BenchmarkResult, _previous: &[BenchmarkResult]) -> BenchmarkResult {
+        self.thread_pool.with_cpu_list(result)
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn calculates_short_precisions() {
+        for precision in 1..=20 {
+            let pi = CPUBenchmark::chudnovsky(precision).unwrap().to_decimal().value();
+            PiVerification::new(precision).verify(&pi.to_string()).unwrap();
+        }
+    }
+}
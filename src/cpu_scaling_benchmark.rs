@@ -0,0 +1,122 @@
+use color_eyre::eyre::Result;
+use console::Style;
+use serde_json::json;
+use crate::benchmark::{Benchmark, BenchmarkKind, Measurement};
+use crate::cpu_benchmark::CPUBenchmark;
+use crate::cpu_thread_pool::CPUThreadPool;
+use crate::interrupt::Interrupt;
+use crate::results::{BenchmarkResult, Parameters, Unit};
+
+///Runs the PI workload on a dedicated thread pool of `threads` workers. One benchmark is registered
+///for each of 1, 2, 4 ... up to the threads of the CPU pool to show how throughput scales with the number of cores.
+pub struct CPUScalingBenchmark {
+    precision: usize,
+    num_calculations: u32,
+    threads: usize,
+    cpu_benchmark: CPUBenchmark,
+}
+
+impl CPUScalingBenchmark {
+    pub fn new(precision: usize, num_calculations: u32, thread_pool: CPUThreadPool, threads: usize) -> Self {
+        Self {
+            precision,
+            num_calculations,
+            threads,
+            cpu_benchmark: CPUBenchmark::new(BenchmarkKind::CpuScaling, precision, num_calculations, thread_pool, threads, true),
+        }
+    }
+
+    ///Stops the calculations early once `interrupt` is set
+    pub fn with_interrupt(mut self, interrupt: Interrupt) -> Self {
+        self.cpu_benchmark = self.cpu_benchmark.with_interrupt(interrupt);
+        self
+    }
+
+    ///Powers of two below `max_threads`, followed by `max_threads` itself
+    pub fn thread_counts(max_threads: usize) -> Vec<usize> {
+        let mut thread_counts = vec![1];
+        while thread_counts.last().unwrap() * 2 < max_threads {
+            thread_counts.push(thread_counts.last().unwrap() * 2);
+        }
+        if max_threads > 1 {
+            thread_counts.push(max_threads);
+        }
+        thread_counts
+    }
+
+    ///Prints throughput, speed-up and parallel efficiency of every thread count as a table
+    pub fn print_scaling(results: &[BenchmarkResult]) {
+        let value_style = Style::new().bright().green().bold();
+
+        println!("{:<10}{:<26}{:<12}Efficiency", "Threads", "Throughput", "Speed-up");
+        for result in results {
+            let threads = result.parameters["threads"].as_u64().unwrap_or(0);
+            let speed_up = result.details["speed_up"].as_f64().unwrap_or(0.0);
+            let efficiency = result.details["efficiency"].as_f64().unwrap_or(0.0);
+            println!("{:<10}{:<26}{:<12}{:.1}%",
+                     threads,
+                     value_style.apply_to(result.unit.format(result.average)),
+                     format!("{:.2}x", speed_up),
+                     efficiency * 100.0);
+        }
+    }
+}
+
+impl Benchmark for CPUScalingBenchmark {
+    fn name(&self) -> String {
+        String::from("cpu_scaling")
+    }
+
+    fn category(&self) -> String {
+        BenchmarkKind::CpuScaling.name()
+    }
+
+    fn parameters(&self) -> Parameters {
+        Parameters::from([
+            (String::from("precision"), json!(self.precision)),
+            (String::from("num_calculations"), json!(self.num_calculations)),
+            (String::from("threads"), json!(self.threads)),
+        ])
+    }
+
+    fn unit(&self) -> Unit {
+        Unit::CalculationsPerSecond
+    }
+
+    fn description(&self) -> String {
+        format!("{} PI calculations with precision {} on {} threads reached",
+                self.num_calculations,
+                self.precision,
+                self.threads)
+    }
+
+    fn progress_message(&self) -> String {
+        format!("Running {} PI calculations with precision {} on {} threads",
+                self.num_calculations,
+                self.precision,
+                self.threads)
+    }
+
+    fn setup(&mut self) -> Result<()> {
+        self.cpu_benchmark.setup()
+    }
+
+    fn one_iteration(&mut self) -> Result<Measurement> {
+        let elapsed = self.cpu_benchmark.calculate()?;
+        Ok(Measurement::throughput(self.num_calculations as f64, elapsed))
+    }
+
+    fn teardown(&mut self) -> Result<()> {
+        self.cpu_benchmark.teardown()
+    }
+
+    ///Speed-up over the single threaded run and the parallel efficiency, i.e. the speed-up per thread
+    fn finish(&self, result: BenchmarkResult, previous: &[BenchmarkResult]) -> BenchmarkResult {
+        let single_thread_throughput = previous.first().map(|r| r.average).unwrap_or(result.average);
+        let speed_up = result.average / single_thread_throughput;
+        let result = result
+            .with_detail("speed_up", json!(speed_up))
+            .with_detail("efficiency", json!(speed_up / self.threads as f64));
+        self.cpu_benchmark.finish(result, previous)
+    }
+}
@@ -0,0 +1,125 @@
+use color_eyre::eyre::{eyre, Result, WrapErr};
+use color_eyre::Section;
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use serde_json::json;
+use crate::results::BenchmarkResult;
+
+///Dedicated rayon pool for the CPU benchmarks. When a CPU list is given,
+///worker `i` is pinned to the `i`-th listed CPU, wrapping around if there are more workers than CPUs.
+#[derive(Clone)]
+pub struct CPUThreadPool {
+    threads: Option<usize>,
+    cpu_list: Vec<usize>,
+}
+
+impl CPUThreadPool {
+    pub fn new(threads: Option<usize>, cpu_list: Vec<usize>) -> Self {
+        Self { threads, cpu_list }
+    }
+
+    ///Explicit thread count, else one thread per listed CPU, else the size of the global rayon pool
+    pub fn threads(&self) -> usize {
+        match self.threads {
+            Some(threads) => threads,
+            None if !self.cpu_list.is_empty() => self.cpu_list.len(),
+            None => rayon::current_num_threads(),
+        }
+    }
+
+    ///Records the CPUs the workers were pinned to in the details of `result`
+    pub fn with_cpu_list(&self, result: BenchmarkResult) -> BenchmarkResult {
+        if self.cpu_list.is_empty() {
+            return result;
+        }
+        result.with_detail("cpu_list", json!(self.cpu_list))
+    }
+
+    ///Parses a list of CPU ids such as `0-3,8,10-11`
+    pub fn parse_cpu_list(value: &str) -> Result<Vec<usize>> {
+        let mut cpus = Vec::new();
+        for range in value.split(',').map(str::trim) {
+            let (first, last) = range.split_once('-').unwrap_or((range, range));
+            let first: usize = first.trim().parse()
+                .map_err(|_| eyre!("--cpu-list '{}' contains an invalid CPU id '{}'", value, first))
+                .suggestion("Use CPU ids and ranges separated by commas, e.g. 0-3,8")?;
+            let last: usize = last.trim().parse()
+                .map_err(|_| eyre!("--cpu-list '{}' contains an invalid CPU id '{}'", value, last))
+                .suggestion("Use CPU ids and ranges separated by commas, e.g. 0-3,8")?;
+            if first > last {
+                return Err(eyre!("--cpu-list '{}' contains the descending range '{}'", value, range));
+            }
+            for cpu in first..=last {
+                if !cpus.contains(&cpu) {
+                    cpus.push(cpu);
+                }
+            }
+        }
+        Ok(cpus)
+    }
+
+    ///Builds a pool of `threads` workers and pins them to the CPU list
+    pub fn build(&self, threads: usize) -> Result<ThreadPool> {
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .thread_name(|index| format!("cpu-benchmark-{}", index))
+            .build()
+            .wrap_err_with(|| format!("Unable to create a thread pool with {} threads", threads))?;
+
+        if !self.cpu_list.is_empty() {
+            pool.broadcast(|context| Self::pin(self.cpu_list[context.index() % self.cpu_list.len()]))
+                .into_iter()
+                .collect::<Result<Vec<()>>>()?;
+        }
+
+        Ok(pool)
+    }
+
+    ///Restricts the calling thread to a single CPU
+    #[cfg(target_os = "linux")]
+    fn pin(cpu: usize) -> Result<()> {
+        if cpu >= libc::CPU_SETSIZE as usize {
+            return Err(eyre!("CPU {} is beyond the largest supported CPU id {}", cpu, libc::CPU_SETSIZE - 1));
+        }
+        unsafe {
+            let mut set: libc::cpu_set_t = std::mem::zeroed();
+            libc::CPU_SET(cpu, &mut set);
+            if libc::sched_setaffinity(0, size_of::<libc::cpu_set_t>(), &set) != 0 {
+                return Err(eyre!("Unable to pin a benchmark thread to CPU {}: {}", cpu, std::io::Error::last_os_error())
+                    .suggestion("Check that the CPU is online and allowed for this process, e.g. with taskset -p"));
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn pin(_cpu: usize) -> Result<()> {
+        Err(eyre!("--cpu-list is only supported on Linux"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_cpu_list_expands_ranges() {
+        assert_eq!(CPUThreadPool::parse_cpu_list("0-3,8,10-11").unwrap(), [0, 1, 2, 3, 8, 10, 11]);
+        assert_eq!(CPUThreadPool::parse_cpu_list("5").unwrap(), [5]);
+        assert_eq!(CPUThreadPool::parse_cpu_list(" 2 , 4 - 5 ").unwrap(), [2, 4, 5]);
+    }
+
+    #[test]
+    fn parse_cpu_list_keeps_the_first_occurrence_of_every_cpu() {
+        assert_eq!(CPUThreadPool::parse_cpu_list("3,1-3,0").unwrap(), [3, 1, 2, 0]);
+    }
+
+    #[test]
+    fn parse_cpu_list_rejects_invalid_lists() {
+        assert!(CPUThreadPool::parse_cpu_list("").is_err());
+        assert!(CPUThreadPool::parse_cpu_list("0,,1").is_err());
+        assert!(CPUThreadPool::parse_cpu_list("a").is_err());
+        assert!(CPUThreadPool::parse_cpu_list("-1").is_err());
+        assert!(CPUThreadPool::parse_cpu_list("0-").is_err());
+        assert!(CPUThreadPool::parse_cpu_list("3-1").is_err());
+    }
+}
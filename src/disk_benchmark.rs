@@ -1,25 +1,43 @@
-use std::{alloc, cmp, env, fs, ptr, slice, thread};
+use std::{alloc, fs, ptr, slice};
 use std::alloc::Layout;
+#[cfg(target_os = "macos")]
 use std::ffi::CString;
 use std::fs::{metadata, File, OpenOptions};
-use indicatif::{DecimalBytes, HumanBytes, HumanCount, HumanDuration, ProgressBar, ProgressStyle};
-use std::io::{BufWriter, Write, BufReader, Read, BufRead};
+use indicatif::DecimalBytes;
+use std::io::{self, Write, Read};
 #[cfg(target_os = "macos")]
 use std::os::fd::FromRawFd;
 #[cfg(target_os = "linux")]
 use std::os::linux::fs::MetadataExt;
 use std::path::Path;
 use std::time::{Duration, Instant, SystemTime};
-use console::Style;
-use libc::{c_int, close, fileno};
-use parse_size::parse_size;
+use color_eyre::eyre::{Result, WrapErr};
+#[cfg(target_os = "macos")]
+use libc::c_int;
 use rand::Rng;
+use serde_json::json;
+use crate::benchmark::{Benchmark, BenchmarkKind, Measurement};
+use crate::interrupt::Interrupt;
+use crate::page_cache::{CacheEviction, PageCache};
+use crate::results::{BenchmarkResult, Parameters, Unit};
 
-trait OpenOptionsExt {
+pub(crate) trait OpenOptionsExt {
     fn disable_buffering(&mut self) -> &mut Self;
+
+    ///Opens `path` unbuffered, or buffered when the file system rejects unbuffered I/O.
+    ///Returns the file and whether it is unbuffered.
+    fn open_unbuffered(&self, path: &str) -> io::Result<(File, bool)>;
 }
 
 impl OpenOptionsExt for OpenOptions {
+    fn open_unbuffered(&self, path: &str) -> io::Result<(File, bool)> {
+        match self.clone().disable_buffering().open(path) {
+            // e.g. tmpfs before Linux 6.6 fails O_DIRECT opens with EINVAL
+            Err(e) if e.kind() == io::ErrorKind::InvalidInput => self.open(path).map(|file| (file, false)),
+            result => result.map(|file| (file, true)),
+        }
+    }
+
     #[cfg(target_os = "linux")]
     fn disable_buffering(&mut self) -> &mut Self {
         use std::os::unix::fs::OpenOptionsExt;
@@ -86,195 +104,291 @@ impl Write for MacDirectIO {
     }
 }
 
+#[cfg(target_os = "macos")]
+impl MacDirectIO {
+    pub fn sync_all(&self) -> std::io::Result<()> {
+        self.file.sync_all()
+    }
+}
+
 // `O_DIRECT` requires all reads and writes
 // to be aligned to the block device's block
 // size.
-pub struct Aligned {
+pub(crate) struct Aligned {
     ptr: ptr::NonNull<u8>,
     layout: Layout
 }
 
 impl Aligned {
-    pub fn new(size: usize, alignment: usize) -> Self {
+    ///Zeroed buffer of `size` bytes, `size` must not be 0
+    pub(crate) fn new(size: usize, alignment: usize) -> Self {
+        assert!(size > 0, "Aligned buffers must not be empty");
         let layout = alloc::Layout::from_size_align(size, alignment).unwrap();
-        let ptr = ptr::NonNull::new(unsafe {alloc::alloc(layout)}).unwrap();
+        let ptr = ptr::NonNull::new(unsafe {alloc::alloc_zeroed(layout)}).unwrap();
         Self { ptr, layout }
     }
 
-    pub fn array<'a>(&self) -> &'a mut [u8] {
-        unsafe { slice::from_raw_parts_mut(self.ptr.cast::<u8>().as_ptr(), self.layout.size()) }
+    pub(crate) fn bytes(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.ptr.as_ptr(), self.layout.size()) }
+    }
+
+    pub(crate) fn array(&mut self) -> &mut [u8] {
+        unsafe { slice::from_raw_parts_mut(self.ptr.as_ptr(), self.layout.size()) }
     }
 }
 
 impl Drop for Aligned {
     fn drop(&mut self) {
         unsafe {
-            alloc::dealloc(self.ptr.cast::<u8>().as_ptr(), self.layout);
+            alloc::dealloc(self.ptr.as_ptr(), self.layout);
         }
     }
 }
 
+///Direction of the sequential transfer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiskOperation {
+    Write,
+    Read,
+}
+
 pub struct DiskBenchmark {
     path: String,
     size: u64,
-    num_iterations: u32,
     buffer_size: usize,
-    alignment_size: usize
+    alignment_size: usize,
+    sync_write: bool,
+    operation: DiskOperation,
+    buffer: Option<Aligned>,
+    ///Least thorough page cache eviction applied before a read iteration
+    cache_eviction: Option<CacheEviction>,
+    ///Whether every iteration used unbuffered I/O, false once the file system rejected it
+    direct_io: Option<bool>,
+    interrupt: Interrupt,
 }
 
 impl DiskBenchmark {
-    pub fn new(path: String, size: u64, num_iterations: u32, buffer_size: u64) -> Self {
+    pub fn new(path: String, size: u64, buffer_size: u64, sync_write: bool, operation: DiskOperation) -> Result<Self> {
         let bs = buffer_size - buffer_size % 1024;
         let s = size - size % 1024;
-        let p = Path::new(&path)
-            .join(format!("{}.diskbenchmark", SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis()))
+        let p = Self::benchmark_file_path(&path, "diskbenchmark");
+        let a = Self::block_alignment(&path)?;
+
+        Ok(Self {path: p, size: s, buffer_size: bs as usize, alignment_size: a, sync_write, operation, buffer: None, cache_eviction: None, direct_io: None, interrupt: Interrupt::new() })
+    }
+
+    ///Stops the transfer early once `interrupt` is set
+    pub fn with_interrupt(mut self, interrupt: Interrupt) -> Self {
+        self.interrupt = interrupt;
+        self
+    }
+
+    ///Unique benchmark file inside `directory`, named after the current time
+    pub fn benchmark_file_path(directory: &str, extension: &str) -> String {
+        Path::new(directory)
+            .join(format!("{}.{}", SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis(), extension))
             .to_str()
             .unwrap()
-            .to_string();
+            .to_string()
+    }
 
+    ///Block size of the file system holding `directory`, which unbuffered I/O has to be aligned to
+    #[cfg_attr(not(target_os = "linux"), allow(unused_variables))]
+    pub fn block_alignment(directory: &str) -> Result<usize> {
         #[cfg(not(target_os = "linux"))]
         let a = 4096;
 
         #[cfg(target_os = "linux")]
-        let a = metadata(path).unwrap().st_blksize();
+        let a = metadata(directory)
+            .wrap_err_with(|| format!("Unable to read the block size of the file system holding {}", directory))?
+            .st_blksize();
 
-        Self {path: p, size: s, num_iterations, buffer_size: bs as usize, alignment_size: a as usize }
+        Ok(a as usize)
     }
 
-    pub fn run(&self){
-        self.run_write();
-        println!();
-        self.run_read();
+    ///Deletes the benchmark file at `path` if it exists, a file which cannot be deleted is left behind
+    pub(crate) fn delete_benchmark_file(path: &str) {
+        if metadata(path).is_ok() {
+            let _ = fs::remove_file(path);
+        }
     }
 
-    fn delete_temp_file(&self) -> bool {
-        if metadata(&self.path).is_ok() {
-            return match fs::remove_file(&self.path) {
-                Ok(_) => {
-                    true
-                }
-                Err(e) => {
-                    false
-                }
-            }
+    ///Adds how the page cache was evicted and whether unbuffered I/O was used to the result of a disk benchmark
+    pub(crate) fn with_file_system_details(result: BenchmarkResult,
+                                           cache_eviction: Option<CacheEviction>,
+                                           direct_io: Option<bool>) -> BenchmarkResult {
+        let result = match cache_eviction {
+            Some(cache_eviction) => result.with_detail("cache_eviction", json!(cache_eviction)),
+            None => result,
+        };
+        match direct_io {
+            Some(direct_io) => result.with_detail("direct_io", json!(direct_io)),
+            None => result,
         }
-
-        true
     }
 
-    fn run_write(&self){
-        let value_style = Style::new().bright().green().bold().underlined();
-        let bar = ProgressBar::new(self.num_iterations as u64)
-            .with_message(format!("Writing {} of size {} {} times... ",
-                                  self.path,
-                                  DecimalBytes(self.size),
-            self.num_iterations));
-        bar.set_style(ProgressStyle::with_template("{msg} [{elapsed}]\n{wide_bar:.cyan/blue} {pos}/{len}")
-            .unwrap()
-            .progress_chars("##-"));
-        bar.enable_steady_tick(Duration::from_secs(1));
-        bar.inc(0);
+    fn buffer(&self) -> &[u8] {
+        self.buffer.as_ref().expect("The buffer is allocated by setup").bytes()
+    }
 
-        let aligned = Aligned::new(self.buffer_size, 4096);
-        let random_bytes = aligned.array();
-        for i in 0..random_bytes.len()
-        {
-            random_bytes[i] = rand::thread_rng().random();
-        }
-        let mut total_elapsed = 0u64;
+    fn buffer_mut(&mut self) -> &mut [u8] {
+        self.buffer.as_mut().expect("The buffer is allocated by setup").array()
+    }
 
-        for _ in 0..self.num_iterations {
-            // #[cfg(target_os = "windows")]
-            // if !crate::win32::Win32::clear_standby_list()
-            // {
-            //     println!("Unable to clear file cache. Result may not be accurate.");
-            // }
+    ///Writes the whole benchmark file from the buffer and returns the number of bytes written and the time taken.
+    ///Deleting the previous file and opening the new one are not timed.
+    fn write_file(&mut self) -> Result<(u64, Duration)> {
+        // #[cfg(target_os = "windows")]
+        // if !crate::win32::Win32::clear_standby_list()
+        // {
+        //     println!("Unable to clear file cache. Result may not be accurate.");
+        // }
 
-            self.delete_temp_file();
+        Self::delete_benchmark_file(&self.path);
 
-            #[cfg(target_os = "macos")]
-            let mut file = MacDirectIO::open(self.path.clone());
+        #[cfg(target_os = "macos")]
+        let mut file = MacDirectIO::open(self.path.clone());
 
-            #[cfg(not(target_os = "macos"))]
-            let mut file = OpenOptions::new()
+        #[cfg(not(target_os = "macos"))]
+        let mut file = {
+            let (file, direct_io) = OpenOptions::new()
                 .write(true)
                 .create(true)
-                .disable_buffering()
-                .open(&self.path)
-                .unwrap();
-
-            let now = Instant::now();
-            let mut remaining_size = self.size;
-            while remaining_size > 0 {
-                file.write_all(random_bytes).unwrap();
-                if remaining_size >= self.buffer_size as u64 {
-                    remaining_size -= self.buffer_size as u64;
-                } else {
-                    remaining_size = 0;
-                }
+                .open_unbuffered(&self.path)
+                .wrap_err_with(|| format!("Unable to create the benchmark file {}", self.path))?;
+            self.direct_io = Some(direct_io && self.direct_io.unwrap_or(true));
+            file
+        };
+
+        let random_bytes = self.buffer();
+        let now = Instant::now();
+        let mut written = 0u64;
+        while written < self.size && !self.interrupt.is_set() {
+            file.write_all(random_bytes)
+                .wrap_err_with(|| format!("Unable to write to the benchmark file {}", self.path))?;
+            written += self.buffer_size as u64;
+        }
+        if self.sync_write && !self.interrupt.is_set() {
+            file.sync_all()
+                .wrap_err_with(|| format!("Unable to flush the benchmark file {}", self.path))?;
+        }
+        Ok((written, now.elapsed()))
+    }
+
+    ///Reads the whole benchmark file into the buffer and returns the number of bytes read and the time taken.
+    ///Opening the file is not timed.
+    fn read_file(&mut self) -> Result<(u64, Duration)> {
+        // #[cfg(target_os = "windows")]
+        // if !crate::win32::Win32::clear_standby_list()
+        // {
+        //     println!("Unable to clear file cache. Result may not be accurate.");
+        // }
+
+        #[cfg(target_os = "macos")]
+        let mut file = MacDirectIO::open(self.path.clone());
+
+        #[cfg(not(target_os = "macos"))]
+        let mut file = {
+            let (file, direct_io) = OpenOptions::new()
+                .read(true)
+                .open_unbuffered(&self.path)
+                .wrap_err_with(|| format!("Unable to open the benchmark file {}", self.path))?;
+            self.direct_io = Some(direct_io && self.direct_io.unwrap_or(true));
+            file
+        };
+
+        let path = self.path.clone();
+        let interrupt = self.interrupt.clone();
+        let read_data = self.buffer_mut();
+        let now = Instant::now();
+        let mut total_read = 0u64;
+        loop {
+            let size = file.read(read_data)
+                .wrap_err_with(|| format!("Unable to read from the benchmark file {}", path))?;
+            if size == 0 || interrupt.is_set() {
+                break;
             }
-            total_elapsed += now.elapsed().as_secs();
-            bar.inc(1);
+            total_read += size as u64;
         }
+        Ok((total_read, now.elapsed()))
+    }
+}
 
-        bar.finish();
-        let average= (self.size * self.num_iterations as u64) / cmp::max(total_elapsed, 1);
+impl Benchmark for DiskBenchmark {
+    fn name(&self) -> String {
+        String::from(match self.operation {
+            DiskOperation::Write => "disk_write",
+            DiskOperation::Read => "disk_read",
+        })
+    }
 
-        println!("Write took {} on average.",
-                 value_style.apply_to(format!("{}/s",DecimalBytes(average))));
+    fn category(&self) -> String {
+        BenchmarkKind::Disk.name()
     }
 
-    fn run_read(&self) {
-        let value_style = Style::new().bright().green().bold().underlined();
-        let bar = ProgressBar::new(self.num_iterations as u64)
-            .with_message(format!("Reading {} of size {} {} times...",
-                                  self.path,
-                                  DecimalBytes(self.size),
-                                self.num_iterations));
-        bar.set_style(ProgressStyle::with_template("{msg} [{elapsed}]\n{wide_bar:.cyan/blue} {pos}/{len}")
-            .unwrap()
-            .progress_chars("##-"));
-        bar.enable_steady_tick(Duration::from_secs(1));
-        bar.inc(0);
-
-        let aligned = Aligned::new(self.buffer_size, 4096);
-        let read_data = aligned.array();
-        let mut total_elapsed = 0u64;
-
-        for _ in 0..self.num_iterations {
-            // #[cfg(target_os = "windows")]
-            // if !crate::win32::Win32::clear_standby_list()
-            // {
-            //     println!("Unable to clear file cache. Result may not be accurate.");
-            // }
-
-            #[cfg(target_os = "macos")]
-            let mut file = MacDirectIO::open(self.path.clone());
-
-            #[cfg(not(target_os = "macos"))]
-            let mut file = OpenOptions::new()
-                .read(true)
-                .disable_buffering()
-                .open(&self.path)
-                .unwrap();
+    fn parameters(&self) -> Parameters {
+        Parameters::from([
+            (String::from("file_size"), json!(self.size)),
+            (String::from("buffer_size"), json!(self.buffer_size)),
+            (String::from("alignment"), json!(self.alignment_size)),
+            (String::from("sync_write"), json!(self.sync_write)),
+        ])
+    }
 
-            let now = Instant::now();
+    fn unit(&self) -> Unit {
+        Unit::BytesPerSecond
+    }
 
+    fn description(&self) -> String {
+        String::from(match self.operation {
+            DiskOperation::Write => "Write took",
+            DiskOperation::Read => "Read took",
+        })
+    }
 
-            let mut size = file.read(read_data).unwrap();
-            while size > 0 {
-                size = file.read(read_data).unwrap();
-            }
-            total_elapsed += now.elapsed().as_secs();
-            bar.inc(1);
+    fn progress_message(&self) -> String {
+        format!("{} {} of size {}",
+                match self.operation {
+                    DiskOperation::Write => "Writing",
+                    DiskOperation::Read => "Reading",
+                },
+                self.path,
+                DecimalBytes(self.size))
+    }
+
+    ///Fills the buffer with random data. The read benchmark also writes the file it reads
+    fn setup(&mut self) -> Result<()> {
+        self.buffer = Some(Aligned::new(self.buffer_size, 4096));
+        for byte in self.buffer_mut().iter_mut()
+        {
+            *byte = rand::rng().random();
         }
+        if self.operation == DiskOperation::Read {
+            self.write_file()?;
+        }
+        self.cache_eviction = None;
+        self.direct_io = None;
+        Ok(())
+    }
 
-        bar.finish();
-        let average= (self.size * self.num_iterations as u64) / cmp::max(total_elapsed, 1);
+    fn one_iteration(&mut self) -> Result<Measurement> {
+        if self.operation == DiskOperation::Read {
+            self.cache_eviction = self.cache_eviction.max(Some(PageCache::evict(&self.path)));
+        }
+        let (bytes, elapsed) = match self.operation {
+            DiskOperation::Write => self.write_file()?,
+            DiskOperation::Read => self.read_file()?,
+        };
+        Ok(Measurement::throughput(bytes as f64, elapsed))
+    }
 
-        println!("Read took {} on average.",
-                 value_style.apply_to(format!("{}/s",DecimalBytes(average))));
+    fn teardown(&mut self) -> Result<()> {
+        Self::delete_benchmark_file(&self.path);
+        self.buffer = None;
+        Ok(())
+    }
 
-        self.delete_temp_file();
+    fn finish(&self, result: BenchmarkResult, _previous: &[BenchmarkResult]) -> BenchmarkResult {
+        Self::with_file_system_details(result, self.cache_eviction, self.direct_io)
     }
-}
\ No newline at end of file
+}
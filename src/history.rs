@@ -0,0 +1,195 @@
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+use color_eyre::eyre::{Result, WrapErr};
+use console::Style;
+use serde::{Deserialize, Serialize};
+use crate::compare::Verdict;
+use crate::results::BenchmarkReport;
+
+const HISTORY_FILE: &str = "history.jsonl";
+
+///One saved run, stored as a single line of the history file
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HistoryEntry {
+    ///Seconds since the Unix epoch when the run finished
+    pub timestamp: u64,
+    pub fingerprint: String,
+    pub report: BenchmarkReport,
+}
+
+///Append-only store of every completed run, kept as JSON lines in `history.jsonl`
+pub struct History {
+    directory: PathBuf,
+}
+
+impl History {
+    ///Opens the history in `directory`, or in the per-user data directory when omitted.
+    ///Returns `None` when there is no per-user data directory, the history is disabled then.
+    pub fn new(directory: Option<&str>) -> Option<Self> {
+        let directory = match directory {
+            Some(directory) => PathBuf::from(directory),
+            None => Self::default_directory()?,
+        };
+        Some(Self { directory })
+    }
+
+    fn default_directory() -> Option<PathBuf> {
+        let data_directory = if cfg!(target_os = "windows") {
+            env::var_os("LOCALAPPDATA").map(PathBuf::from)
+        } else {
+            env::var_os("XDG_DATA_HOME")
+                .map(PathBuf::from)
+                .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("share")))
+        };
+        data_directory.map(|directory| directory.join("env_benchmark"))
+    }
+
+    fn file(&self) -> PathBuf {
+        self.directory.join(HISTORY_FILE)
+    }
+
+    pub fn append(&self, report: &BenchmarkReport) -> Result<()> {
+        let entry = HistoryEntry {
+            timestamp: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+            fingerprint: report.system.fingerprint(),
+            report: report.clone(),
+        };
+        let line = serde_json::to_string(&entry).wrap_err("Unable to serialize benchmark results")?;
+
+        fs::create_dir_all(&self.directory)
+            .wrap_err_with(|| format!("Unable to create the history directory {}", self.directory.display()))?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.file())
+            .wrap_err_with(|| format!("Unable to open the history file {}", self.file().display()))?;
+        writeln!(file, "{}", line).wrap_err_with(|| format!("Unable to write to the history file {}", self.file().display()))
+    }
+
+    pub fn load(&self) -> Result<Vec<HistoryEntry>> {
+        let content = match fs::read_to_string(self.file()) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e).wrap_err_with(|| format!("Unable to read the history file {}", self.file().display())),
+        };
+        content.lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(i, line)| serde_json::from_str(line)
+                .wrap_err_with(|| format!("Line {} of {} is not a valid history entry", i + 1, self.file().display())))
+            .collect()
+    }
+
+    ///Lists the last `limit` runs of `host` and the trend of every benchmark over those runs.
+    ///`benchmark` restricts the trends to results with that name.
+    pub fn print(&self, host: &str, benchmark: Option<&str>, limit: usize) -> Result<()> {
+        let entries = self.load()?;
+        let entries: Vec<&HistoryEntry> = entries.iter()
+            .filter(|entry| entry.report.system.host_name == host)
+            .collect();
+        let entries = &entries[entries.len().saturating_sub(limit)..];
+        if entries.is_empty() {
+            println!("No runs of {} found in {}.", host, self.file().display());
+            return Ok(());
+        }
+
+        let value_style = Style::new().bright().green().bold();
+        println!("Runs of {}:", host);
+        for (i, entry) in entries.iter().enumerate() {
+            println!("    {:<26}{} results",
+                     Self::format_timestamp(entry.timestamp),
+                     entry.report.results.len());
+            if i > 0 && entry.fingerprint != entries[i - 1].fingerprint {
+                println!("    {:<26}system changed to {}", "", value_style.apply_to(&entry.fingerprint));
+            }
+        }
+        println!();
+
+        let mut labels: Vec<String> = Vec::new();
+        for result in entries.iter().flat_map(|entry| &entry.report.results) {
+            if benchmark.is_none_or(|name| result.name == name) && !labels.contains(&result.label()) {
+                labels.push(result.label());
+            }
+        }
+
+        for label in labels {
+            println!("{}", label);
+            let mut first: Option<f64> = None;
+            let mut previous: Option<f64> = None;
+            let mut runs = 0;
+            for entry in entries {
+                let Some(result) = entry.report.results.iter().find(|result| result.label() == label) else {
+                    continue;
+                };
+                let higher_is_better = result.unit.higher_is_better();
+                let change = previous
+                    .map(|previous| Self::change_style(previous, result.average, higher_is_better)
+                        .apply_to(format!("{:+.1}%", (result.average / previous - 1.0) * 100.0))
+                        .to_string())
+                    .unwrap_or_default();
+                println!("    {:<26}{:<26}{}",
+                         Self::format_timestamp(entry.timestamp),
+                         result.unit.format(result.average),
+                         change);
+                first.get_or_insert(result.average);
+                previous = Some(result.average);
+                runs += 1;
+            }
+            if let (Some(first), Some(last), true) = (first, previous, runs > 1) {
+                println!("    {:<26}{:+.1}% since the first run", "Trend:", (last / first - 1.0) * 100.0);
+            }
+            println!();
+        }
+
+        Ok(())
+    }
+
+    fn change_style(previous: f64, current: f64, higher_is_better: bool) -> Style {
+        if current == previous {
+            Verdict::Similar.style()
+        } else if (current > previous) == higher_is_better {
+            Verdict::Faster.style()
+        } else {
+            Verdict::Slower.style()
+        }
+    }
+
+    ///Formats seconds since the Unix epoch as a UTC date and time
+    fn format_timestamp(timestamp: u64) -> String {
+        // Civil from days, see https://howardhinnant.github.io/date_algorithms.html
+        let days = (timestamp / 86400) as i64 + 719_468;
+        let era = days.div_euclid(146_097);
+        let day_of_era = days.rem_euclid(146_097);
+        let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month_index = (5 * day_of_year + 2) / 153;
+        let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+        let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+        let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+        let seconds = timestamp % 86400;
+        format!("{:04}-{:02}-{:02} {:02}:{:02} UTC", year, month, day, seconds / 3600, seconds % 3600 / 60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_timestamp_as_utc_date_and_time() {
+        assert_eq!(History::format_timestamp(0), "1970-01-01 00:00 UTC");
+        assert_eq!(History::format_timestamp(1_791_340_800), "2026-10-07 02:40 UTC");
+        assert_eq!(History::format_timestamp(4_102_444_800), "2100-01-01 00:00 UTC");
+    }
+
+    #[test]
+    fn format_timestamp_on_leap_days() {
+        assert_eq!(History::format_timestamp(951_782_400), "2000-02-29 00:00 UTC");
+        assert_eq!(History::format_timestamp(951_868_799), "2000-02-29 23:59 UTC");
+        assert_eq!(History::format_timestamp(1_709_212_079), "2024-02-29 13:07 UTC");
+    }
+}
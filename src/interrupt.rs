@@ -0,0 +1,42 @@
+use std::process;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use color_eyre::eyre::{Result, WrapErr};
+
+///Exit status of a process terminated by SIGINT
+pub const INTERRUPTED_EXIT_CODE: u8 = 130;
+
+///Cancellation of a run. Benchmarks poll `is_set` between units of work and stop early,
+///cleaning up their files and keeping the iterations already completed.
+///Clones share the same flag, so a run can be stopped from another thread or by Ctrl-C.
+#[derive(Debug, Clone, Default)]
+pub struct Interrupt {
+    interrupted: Arc<AtomicBool>,
+}
+
+impl Interrupt {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///Sets this handle on Ctrl-C. A second Ctrl-C exits immediately without cleaning up.
+    ///Only one handler can be installed per process.
+    pub fn install(&self) -> Result<()> {
+        let interrupt = self.clone();
+        ctrlc::set_handler(move || {
+            if interrupt.interrupted.swap(true, Ordering::SeqCst) {
+                process::exit(INTERRUPTED_EXIT_CODE as i32);
+            }
+            eprintln!("Interrupted, stopping the running benchmark. Press Ctrl-C again to exit immediately.");
+        }).wrap_err("Unable to install the Ctrl-C handler")
+    }
+
+    ///Stops the run using this handle after the unit of work in progress
+    pub fn set(&self) {
+        self.interrupted.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_set(&self) -> bool {
+        self.interrupted.load(Ordering::SeqCst)
+    }
+}
@@ -0,0 +1,146 @@
+use std::fmt;
+use std::iter;
+use std::time::{Duration, Instant};
+use indicatif::HumanDuration;
+
+///When a benchmark stops repeating its measurement
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IterationLimit {
+    ///Exactly this many iterations
+    Count(u32),
+    ///As many iterations as fit into the time budget, but at least `min`
+    TimeBounded { budget: Duration, min: u32 },
+}
+
+///How often a benchmark repeats its measurement
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Iterations {
+    pub limit: IterationLimit,
+    ///Iterations run before the measured ones and excluded from the statistics
+    pub warmup: u32,
+}
+
+///One iteration handed out by `Iterations::iter`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Iteration {
+    pub warmup: bool,
+    ///Position among the warm-up or the measured iterations, starting at 1
+    pub number: u32,
+}
+
+impl Iterations {
+    pub fn new(limit: IterationLimit, warmup: u32) -> Self {
+        Self { limit, warmup }
+    }
+
+    ///Number of measured iterations known to run, used to size buffers
+    pub fn expected(&self) -> u32 {
+        match self.limit {
+            IterationLimit::Count(count) => count,
+            IterationLimit::TimeBounded { min, .. } => min,
+        }
+    }
+
+    ///Number of iterations including the warm-up, used to size progress bars
+    pub fn expected_with_warmup(&self) -> u32 {
+        self.warmup + self.expected()
+    }
+
+    pub fn is_time_bounded(&self) -> bool {
+        matches!(self.limit, IterationLimit::TimeBounded { .. })
+    }
+
+    ///Iterations of one of `parts` benchmarks sharing the time budget evenly. A fixed count is not divided
+    pub fn share(&self, parts: usize) -> Self {
+        let limit = match self.limit {
+            IterationLimit::TimeBounded { budget, min } => IterationLimit::TimeBounded {
+                budget: budget / parts.max(1) as u32,
+                min,
+            },
+            limit => limit,
+        };
+        Self { limit, ..*self }
+    }
+
+    ///The warm-up iterations followed by the measured ones.
+    ///A time budget starts counting down after the warm-up.
+    pub fn iter(&self) -> impl Iterator<Item = Iteration> {
+        let iterations = *self;
+        let mut index = 0;
+        let mut started: Option<Instant> = None;
+        iter::from_fn(move || {
+            index += 1;
+            if index <= iterations.warmup {
+                return Some(Iteration { warmup: true, number: index });
+            }
+            let measured = index - iterations.warmup - 1;
+            let started = *started.get_or_insert_with(Instant::now);
+            let more = match iterations.limit {
+                IterationLimit::Count(count) => measured < count,
+                IterationLimit::TimeBounded { budget, min } => measured < min || started.elapsed() < budget,
+            };
+            more.then_some(Iteration { warmup: false, number: measured + 1 })
+        })
+    }
+}
+
+impl fmt::Display for Iterations {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.limit {
+            IterationLimit::Count(count) => write!(f, "{} times", count)?,
+            IterationLimit::TimeBounded { budget, min } => write!(f, "for {} and at least {} times", HumanDuration(budget), min)?,
+        }
+        if self.warmup > 0 {
+            write!(f, " after {} warm-up iteration{}", self.warmup, if self.warmup == 1 { "" } else { "s" })?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+    use super::*;
+
+    fn numbers(iterations: Iterations) -> Vec<(bool, u32)> {
+        iterations.iter().map(|iteration| (iteration.warmup, iteration.number)).collect()
+    }
+
+    #[test]
+    fn numbers_the_warmup_and_the_measured_iterations_separately() {
+        let iterations = Iterations::new(IterationLimit::Count(3), 2);
+        assert_eq!(numbers(iterations), [(true, 1), (true, 2), (false, 1), (false, 2), (false, 3)]);
+        assert_eq!(iterations.expected_with_warmup(), 5);
+    }
+
+    #[test]
+    fn stops_after_the_count() {
+        assert_eq!(numbers(Iterations::new(IterationLimit::Count(2), 0)), [(false, 1), (false, 2)]);
+        assert_eq!(numbers(Iterations::new(IterationLimit::Count(0), 1)), [(true, 1)]);
+    }
+
+    #[test]
+    fn runs_the_minimum_once_the_budget_has_passed() {
+        let iterations = Iterations::new(IterationLimit::TimeBounded { budget: Duration::ZERO, min: 3 }, 1);
+        assert_eq!(numbers(iterations), [(true, 1), (false, 1), (false, 2), (false, 3)]);
+    }
+
+    #[test]
+    fn repeats_until_the_budget_has_passed() {
+        let budget = Duration::from_millis(30);
+        let iterations = Iterations::new(IterationLimit::TimeBounded { budget, min: 1 }, 0);
+        let started = Instant::now();
+        let count = iterations.iter().inspect(|_| thread::sleep(Duration::from_millis(10))).count();
+        assert!(count >= 2, "only {} iterations", count);
+        assert!(started.elapsed() >= budget);
+    }
+
+    #[test]
+    fn shares_the_budget_but_not_the_count() {
+        let time_bounded = Iterations::new(IterationLimit::TimeBounded { budget: Duration::from_secs(30), min: 3 }, 1);
+        assert_eq!(time_bounded.share(3).limit, IterationLimit::TimeBounded { budget: Duration::from_secs(10), min: 3 });
+        assert_eq!(time_bounded.share(0), time_bounded);
+        let count = Iterations::new(IterationLimit::Count(5), 1);
+        assert_eq!(count.share(3), count);
+    }
+}
@@ -0,0 +1,56 @@
+//!Benchmarks comparing the CPU, memory and disk performance of virtual and physical machines.
+//!
+//!`run` executes the built-in benchmarks and returns a `BenchmarkReport` holding the system information and
+//!every result with its statistics. `Registry` runs a custom selection, including workloads implementing `Benchmark`.
+
+pub mod benchmark;
+pub mod compare;
+pub mod cpu_benchmark;
+pub mod cpu_scaling_benchmark;
+pub mod cpu_thread_pool;
+pub mod disk_benchmark;
+pub mod history;
+pub mod interrupt;
+pub mod iterations;
+pub mod memory_benchmark;
+pub mod memory_latency_benchmark;
+pub mod page_cache;
+pub mod pi_verification;
+mod progress;
+pub mod random_disk_benchmark;
+pub mod registry;
+pub mod reporter;
+pub mod results;
+pub mod score;
+pub mod settings;
+pub mod statistics;
+pub mod sync_disk_benchmark;
+#[cfg(target_os = "windows")]
+mod win32;
+
+use color_eyre::eyre::Result;
+pub use crate::benchmark::{Benchmark, BenchmarkKind, Measurement};
+pub use crate::interrupt::Interrupt;
+pub use crate::registry::Registry;
+pub use crate::reporter::{JsonLinesReporter, PlainReporter, QuietReporter, Reporter, TerminalReporter};
+pub use crate::results::{BenchmarkReport, BenchmarkResult, SystemInfo, Unit};
+pub use crate::settings::Settings;
+
+///Runs the built-in benchmarks accepted by `is_selected` quietly and returns their results.
+///A benchmark which fails keeps the iterations it completed and its error in its result, see `BenchmarkReport::failed`.
+///Setting `settings.interrupt`, e.g. from another thread or on Ctrl-C with `Interrupt::install`, stops the run early.
+pub fn run(settings: &Settings, is_selected: impl Fn(BenchmarkKind) -> bool) -> Result<BenchmarkReport> {
+    run_with_reporter(settings, is_selected, &mut QuietReporter)
+}
+
+///Runs the built-in benchmarks accepted by `is_selected`, passing their progress and results to `reporter`
+pub fn run_with_reporter(settings: &Settings,
+                         is_selected: impl Fn(BenchmarkKind) -> bool,
+                         reporter: &mut dyn Reporter) -> Result<BenchmarkReport> {
+    settings.validate(&is_selected)?;
+    let mut report = BenchmarkReport::new(SystemInfo::collect());
+    reporter.run_started(&report.system);
+    Registry::builtin(settings)?.run(|category| BenchmarkKind::from_name(category).is_some_and(&is_selected), &mut report, reporter);
+    report.interrupted = settings.interrupt.is_set();
+    Ok(report)
+}
@@ -1,118 +1,364 @@
-mod cpu_benchmark;
-mod disk_benchmark;
-#[cfg(target_os = "windows")]
-mod win32;
-
-use std::{env, mem, thread};
-use std::fs::metadata;
-use std::hint::black_box;
-use std::io::{Read};
-use std::path::Path;
-use std::sync::Arc;
-use std::thread::available_parallelism;
-use std::time::Instant;
-use clap::{arg, Arg, Parser};
-use console::{Style};
-use indicatif::DecimalBytes;
-use parse_size::{parse_size, Error};
-use sysinfo::{System};
-use crate::cpu_benchmark::CPUBenchmark;
-use crate::disk_benchmark::DiskBenchmark;
+mod config;
+
+use std::env;
+use std::io::{stdin, stdout, IsTerminal};
+use std::process::ExitCode;
+use std::time::Duration;
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
+use color_eyre::eyre::{eyre, Result};
+use color_eyre::Section;
+use crate::config::{Config, Profile};
+use env_benchmark::compare::{RunComparison, REGRESSION_EXIT_CODE};
+use env_benchmark::history::History;
+use env_benchmark::interrupt::INTERRUPTED_EXIT_CODE;
+use env_benchmark::iterations::{IterationLimit, Iterations};
+use env_benchmark::results::OutputFormat;
+use env_benchmark::score::{ReferenceProfile, Score};
+use env_benchmark::sync_disk_benchmark::SyncMethod;
+use env_benchmark::{BenchmarkKind, BenchmarkReport, Interrupt, Settings, SystemInfo};
+use env_benchmark::{JsonLinesReporter, PlainReporter, QuietReporter, Reporter, TerminalReporter};
 
 ///Environment benchmark program to compare relative performance between virtual and physical machine
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    ///Built-in benchmark suite. Arguments given on the command line take precedence
+    #[arg(long, value_enum)]
+    profile: Option<Profile>,
+
+    ///TOML file declaring the benchmarks to run and their parameters, applied on top of --profile.
+    ///Arguments given on the command line take precedence
+    #[arg(long)]
+    config: Option<String>,
+
     ///Total number of calculations to execute for CPU multicore test
     #[arg(short, long, default_value_t = 20)]
     num_calculations: u32,
 
-    ///Number of iterations to execute to get the average result
+    ///Number of iterations to execute to get the average result. Ignored when --duration is set
     #[arg(short, long, default_value_t = 5)]
     iterations: u32,
 
+    ///Repeat the iterations until this many seconds have passed instead of a fixed number of times.
+    ///The time applies to every benchmark selected with --only and is split evenly between its workloads,
+    ///e.g. the working sets of memory-latency
+    #[arg(long)]
+    duration: Option<u32>,
+
+    ///Least number of iterations run by every workload when --duration is set, even when its share of the time has passed
+    #[arg(long, default_value_t = 3)]
+    min_iterations: u32,
+
+    ///Iterations run before the measured ones to warm up caches, thread pools and CPU clocks.
+    ///Their timings are kept in the results but excluded from the statistics
+    #[arg(long, default_value_t = 1)]
+    warmup: u32,
+
     ///PI accuracy to number of  decimal points
     #[arg(short, long, default_value_t = 3000)]
     pi_precision: u32,
 
+    ///Number of threads used by the CPU tests. Defaults to one per CPU in --cpu-list, else one per core
+    #[arg(long)]
+    threads: Option<u32>,
+
+    ///Pin the CPU test threads to these CPUs, e.g. 0-3,8. Linux only
+    #[arg(long)]
+    cpu_list: Option<String>,
+
     ///Size of benchmark file for testing file read and write performance
     #[arg(short, long, default_value = "4GB")]
     filesize: String,
 
     ///Read and Write buffer size
-    #[arg(short, long, default_value = "100MB")]
+    #[arg(short, long, default_value = "100MiB")]
     buffer_size: String,
 
     ///Location of benchmark file. Change this to benchmark other storage locations
     #[arg(short, long, default_value_t = env::temp_dir().into_os_string().into_string().unwrap())]
-    temp_file_directory: String
+    temp_file_directory: String,
+
+    ///Include a final flush to stable storage in the timed region of the sequential write test
+    #[arg(long)]
+    sync_write: bool,
+
+    ///Size of benchmark file for testing random access performance
+    #[arg(long, default_value = "1GB")]
+    random_filesize: String,
+
+    ///Block size of every random read and write
+    #[arg(long, default_value = "4KiB")]
+    block_size: String,
+
+    ///Percentage of random operations that are reads, the rest are writes
+    #[arg(long, default_value_t = 70, value_parser = clap::value_parser!(u32).range(0..=100))]
+    read_percent: u32,
+
+    ///Number of random operations kept in flight at the same time
+    #[arg(long, default_value_t = 32)]
+    queue_depth: u32,
+
+    ///Number of random operations per iteration
+    #[arg(long, default_value_t = 10000)]
+    random_operations: u32,
+
+    ///System call used to flush every record in the durability test
+    #[arg(long, value_enum, default_value_t = SyncMethod::Data)]
+    sync_method: SyncMethod,
+
+    ///Size of every record written before a flush in the durability test
+    #[arg(long, default_value = "4KiB")]
+    sync_record_size: String,
+
+    ///Number of flushed records per iteration of the durability test
+    #[arg(long, default_value_t = 1000)]
+    sync_operations: u32,
+
+    ///Size of each of the three arrays used by the memory bandwidth test
+    #[arg(long, default_value = "128MiB")]
+    memory_array_size: String,
+
+    ///Largest working set walked by the memory latency test
+    #[arg(long, default_value = "512MiB")]
+    latency_max_size: String,
+
+    ///Run only these benchmarks, separated by commas. All benchmarks except cpu-scaling run when omitted
+    #[arg(long, value_enum, value_delimiter = ',')]
+    only: Vec<BenchmarkKind>,
+
+    ///Skip these benchmarks, separated by commas
+    #[arg(long, value_enum, value_delimiter = ',')]
+    skip: Vec<BenchmarkKind>,
+
+    ///Format of the benchmark results printed to standard output
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Text)]
+    output: OutputFormat,
+
+    ///Save the benchmark results as a JSON document to this file
+    #[arg(long)]
+    output_file: Option<String>,
+
+    ///Directory of the result history. Defaults to env_benchmark in the per-user data directory.
+    ///A run which cannot be saved to it only prints a warning
+    #[arg(long, global = true)]
+    history_dir: Option<String>,
+
+    ///Do not save the results of this run to the result history
+    #[arg(long)]
+    no_history: bool,
+
+    ///Result file to gate this run against. Exits with status 2 when a benchmark regressed beyond --max-regression
+    ///or a benchmark of the baseline did not run
+    #[arg(long)]
+    baseline: Option<String>,
+
+    ///Do not fail the --baseline gate for benchmarks of the baseline which did not run, e.g. because of --skip
+    #[arg(long)]
+    allow_missing: bool,
+
+    ///Percentage a benchmark may be slower than the --baseline before it counts as a regression
+    #[arg(long, default_value_t = 5.0)]
+    max_regression: f64,
+
+    ///Result file of the reference machine the scores are relative to. Defaults to the built-in reference machine
+    #[arg(long)]
+    reference: Option<String>,
+
+    ///Exit as soon as the benchmarks complete instead of waiting for 'q' to be pressed.
+    ///Implied when standard input or output is not a terminal
+    #[arg(long, visible_alias = "batch")]
+    no_wait: bool
 }
 
-fn main() {
-    let args = Args::parse();
+impl Args {
+    fn is_selected(&self, kind: BenchmarkKind) -> bool {
+        let only = if self.only.is_empty() { kind.is_default() } else { self.only.contains(&kind) };
+        only && !self.skip.contains(&kind)
+    }
+
+    ///Benchmark settings with every size parsed into bytes
+    fn settings(&self) -> Result<Settings> {
+        let settings = Settings {
+            num_calculations: Settings::positive("--num-calculations", self.num_calculations)?,
+            iterations: Iterations::new(match self.duration {
+                Some(seconds) => IterationLimit::TimeBounded {
+                    budget: Duration::from_secs(Settings::positive("--duration", seconds)? as u64),
+                    min: Settings::positive("--min-iterations", self.min_iterations)?,
+                },
+                None => IterationLimit::Count(Settings::positive("--iterations", self.iterations)?),
+            }, self.warmup),
+            precision: Settings::positive("--pi-precision", self.pi_precision)? as usize,
+            threads: self.threads.map(|threads| Settings::positive("--threads", threads)).transpose()?.map(|threads| threads as usize),
+            cpu_list: self.cpu_list.as_deref().map(Settings::cpu_list).transpose()?.unwrap_or_default(),
+            file_size: Settings::size("--filesize", &self.filesize)?,
+            buffer_size: Settings::size("--buffer-size", &self.buffer_size)?,
+            sync_write: self.sync_write,
+            random_file_size: Settings::size("--random-filesize", &self.random_filesize)?,
+            block_size: Settings::size("--block-size", &self.block_size)?,
+            read_percent: self.read_percent,
+            queue_depth: Settings::positive("--queue-depth", self.queue_depth)?,
+            random_operations: Settings::positive("--random-operations", self.random_operations)?,
+            sync_method: self.sync_method,
+            sync_record_size: Settings::size("--sync-record-size", &self.sync_record_size)?,
+            sync_operations: Settings::positive("--sync-operations", self.sync_operations)?,
+            memory_array_size: Settings::size("--memory-array-size", &self.memory_array_size)?,
+            latency_max_size: Settings::size("--latency-max-size", &self.latency_max_size)?,
+            temp_file_directory: Settings::directory("--temp-file-directory", &self.temp_file_directory)?,
+            interrupt: Interrupt::new(),
+        };
+        if self.max_regression < 0.0 {
+            return Err(eyre!("--max-regression must not be negative"));
+        }
+        Ok(settings)
+    }
+}
 
-    let num_calculations = (args.num_calculations > 0).then(|| args.num_calculations).or_else(|| Some(20)).unwrap();
-    let num_iterations = (args.iterations > 0).then(|| args.iterations).or_else(|| Some(5)).unwrap();
-    let precision = (args.pi_precision > 0).then(|| args.pi_precision).or_else(|| Some(3000)).unwrap() as usize;
-    let mut file_size = parse_size("4GB").unwrap();
-    let mut buffer_size = parse_size("100MB").unwrap();
-    let mut file_path = env::temp_dir().into_os_string().into_string().unwrap();
+#[derive(Subcommand, Debug)]
+enum Command {
+    ///Compare two saved result files and print the relative performance of every benchmark
+    Compare {
+        ///Result file used as the reference, e.g. from a physical machine
+        baseline: String,
 
-    match parse_size(args.filesize) {
-        Ok(f) => {
-            file_size = f;
+        ///Result file compared against the baseline, e.g. from a virtual machine
+        candidate: String,
+
+        ///Percentage difference below which two results are considered similar
+        #[arg(long, default_value_t = 5.0)]
+        threshold: f64,
+    },
+    ///List the saved runs of a host and the trend of every benchmark over time
+    History {
+        ///Host whose runs are shown. Defaults to this machine
+        #[arg(long)]
+        host: Option<String>,
+
+        ///Only show the trend of this benchmark, e.g. cpu_multi
+        #[arg(long)]
+        benchmark: Option<String>,
+
+        ///Number of most recent runs shown
+        #[arg(long, default_value_t = 10)]
+        limit: usize,
+    },
+}
+
+fn main() -> Result<ExitCode> {
+    color_eyre::install()?;
+    let matches = Args::command().get_matches();
+    let mut args = Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    if let Some(profile) = args.profile {
+        Config::profile(profile).apply(&mut args, &matches);
+    }
+    if let Some(config_file) = args.config.clone() {
+        Config::load(&config_file)?.apply(&mut args, &matches);
+    }
+
+    match args.command {
+        Some(Command::Compare { ref baseline, ref candidate, threshold }) => {
+            RunComparison::new(baseline, candidate, threshold)?.print();
+            Ok(ExitCode::SUCCESS)
+        }
+        Some(Command::History { ref host, ref benchmark, limit }) => {
+            let host = host.clone().unwrap_or(SystemInfo::collect().host_name);
+            History::new(args.history_dir.as_deref())
+                .ok_or_else(|| eyre!("Unable to find a directory for the result history")
+                    .suggestion("Set one with --history-dir"))?
+                .print(&host, benchmark.as_deref(), limit)?;
+            Ok(ExitCode::SUCCESS)
         }
-        Err(_) => {}
+        None => run_benchmarks(args),
+    }
+}
+
+fn run_benchmarks(args: Args) -> Result<ExitCode> {
+    let settings = args.settings()?;
+    let baseline = args.baseline.as_deref().map(BenchmarkReport::load).transpose()?;
+    let reference = match &args.reference {
+        Some(reference_file) => ReferenceProfile::load(reference_file)?,
+        None => ReferenceProfile::builtin(),
+    };
+    settings.interrupt.install()?;
+
+    let is_terminal = console::Term::stdout().is_term();
+    let mut reporter: Box<dyn Reporter> = match args.output {
+        OutputFormat::Text if is_terminal => Box::new(TerminalReporter::new(true)),
+        OutputFormat::Text | OutputFormat::Plain => Box::new(PlainReporter::new(true)),
+        OutputFormat::Quiet => Box::new(QuietReporter),
+        OutputFormat::Json if is_terminal => Box::new(TerminalReporter::new(false)),
+        OutputFormat::Json => Box::new(PlainReporter::new(false)),
+        OutputFormat::JsonLines => Box::new(JsonLinesReporter::new(stdout())),
+    };
+
+    let mut report = env_benchmark::run_with_reporter(&settings, |kind| args.is_selected(kind), reporter.as_mut())?;
+    report.score = Score::new(&report.results, &reference);
+    if let Some(score) = &report.score {
+        reporter.score(score);
+    }
+
+    if let Some(output_file) = &args.output_file {
+        report.save(output_file)?;
+    }
+
+    reporter.run_finished(&report);
+    if args.output == OutputFormat::Json {
+        println!("{}", report.to_json()?);
     }
 
-    match parse_size(args.buffer_size) {
-        Ok(f) => {
-            buffer_size = f;
+    if report.interrupted {
+        return Ok(ExitCode::from(INTERRUPTED_EXIT_CODE));
+    }
+
+    let failed = report.failed();
+    if !failed.is_empty() {
+        for result in failed {
+            eprintln!("{} failed: {}", result.label(), result.error.as_deref().unwrap_or_default());
         }
-        Err(_) => {}
-    }
-
-    let metadata = metadata(&args.temp_file_directory);
-    if metadata.is_ok() && metadata.unwrap().is_dir() {
-        file_path = args.temp_file_directory;
-    }
-
-    let mut sys = System::new_all();
-    sys.refresh_all();
-    let system_info_style = Style::new().bright().green().bold();
-    println!("{:<30}{:<10}", "System name:", system_info_style.apply_to(System::name()
-        .unwrap_or(String::from("Unknown"))));
-    println!("{:<30}{:<10}", "System kernel version:", system_info_style.apply_to(System::kernel_version()
-        .unwrap_or(String::from("Unknown"))));
-    println!("{:<30}{:<10}", "System OS version:", system_info_style.apply_to(System::long_os_version()
-                .unwrap_or(String::from("Unknown"))));
-    println!("{:<30}{:<10}", "Number of CPU threads:", system_info_style.apply_to(sys.cpus().len()));
-    println!("{:<30}{:<10}", "Available memory:", system_info_style.apply_to(format!("{}/{}", DecimalBytes(sys.available_memory()), DecimalBytes(sys.total_memory()))));
-    println!();
-
-    let mut cpu_benchmark = Arc::new(CPUBenchmark::new(precision,
-                                                   num_iterations,
-                                                   1));
-    cpu_benchmark.run();
-    println!();
-
-    cpu_benchmark = Arc::new(CPUBenchmark::new(precision,
-                                                num_iterations,
-                                                num_calculations));
-    cpu_benchmark.run();
-    println!();
-
-    let disk_benchmark = DiskBenchmark::new(file_path,
-                                            file_size,
-                                            num_iterations,
-                                            buffer_size);
-    disk_benchmark.run();
-    println!();
-
-    println!("Benchmark completed!");
+        return Ok(ExitCode::FAILURE);
+    }
+
+    let mut failed_gate = false;
+    if let (Some(baseline), Some(baseline_file)) = (baseline, &args.baseline) {
+        let comparison = RunComparison::from_reports(baseline, report.clone(), args.max_regression);
+        comparison.print_regressions(baseline_file);
+        comparison.print_missing(baseline_file);
+        let missing = !args.allow_missing && !comparison.missing().is_empty();
+        failed_gate = !comparison.regressions().is_empty() || missing;
+    }
+
+    if !args.no_history {
+        save_history(args.history_dir.as_deref(), &report);
+    }
+
+    if failed_gate {
+        return Ok(ExitCode::from(REGRESSION_EXIT_CODE));
+    }
+
     let term = console::Term::stdout();
-    let mut character = term.read_char().unwrap();
-    while character != 'q' {
-        character = term.read_char().unwrap();
+    if args.no_wait || !stdin().is_terminal() || !term.is_term() {
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    while let Ok(character) = term.read_char() {
+        if character == 'q' {
+            break;
+        }
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+///Appends the run to the result history. The history is optional, so a failure only warns
+///instead of discarding the results of the run.
+fn save_history(history_dir: Option<&str>, report: &BenchmarkReport) {
+    let Some(history) = History::new(history_dir) else {
+        eprintln!("The run was not saved to the result history, no directory was found for it. Set one with --history-dir.");
+        return;
+    };
+    if let Err(e) = history.append(report) {
+        eprintln!("The run was not saved to the result history: {:#}", e);
     }
 }
@@ -0,0 +1,148 @@
+use std::hint::black_box;
+use std::mem::size_of;
+use std::time::Instant;
+use color_eyre::eyre::Result;
+use indicatif::DecimalBytes;
+use rayon::iter::{IndexedParallelIterator, IntoParallelRefIterator, IntoParallelRefMutIterator, ParallelIterator};
+use serde_json::json;
+use crate::benchmark::{Benchmark, BenchmarkKind, Measurement};
+use crate::results::{Parameters, Unit};
+
+const SCALAR: f64 = 3.0;
+
+///STREAM kernels, see https://www.cs.virginia.edu/stream/ref.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kernel {
+    ///c = a
+    Copy,
+    ///b = scalar * c
+    Scale,
+    ///c = a + b
+    Add,
+    ///a = b + scalar * c
+    Triad,
+}
+
+impl Kernel {
+    pub const ALL: [Kernel; 4] = [Kernel::Copy, Kernel::Scale, Kernel::Add, Kernel::Triad];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Kernel::Copy => "copy",
+            Kernel::Scale => "scale",
+            Kernel::Add => "add",
+            Kernel::Triad => "triad",
+        }
+    }
+
+    ///Number of arrays read or written by one pass of the kernel
+    fn arrays_touched(&self) -> usize {
+        match self {
+            Kernel::Copy | Kernel::Scale => 2,
+            Kernel::Add | Kernel::Triad => 3,
+        }
+    }
+}
+
+///STREAM-like memory bandwidth benchmark running one kernel on one thread or across the rayon pool
+pub struct MemoryBenchmark {
+    array_size: usize,
+    kernel: Kernel,
+    parallel: bool,
+    arrays: [Vec<f64>; 3],
+}
+
+impl MemoryBenchmark {
+    pub fn new(array_size: u64, kernel: Kernel, parallel: bool) -> Self {
+        let elements = (array_size as usize / size_of::<f64>()).max(1);
+        Self { array_size: elements * size_of::<f64>(), kernel, parallel, arrays: Default::default() }
+    }
+
+    fn threads(&self) -> usize {
+        if self.parallel { rayon::current_num_threads() } else { 1 }
+    }
+
+    fn run_kernel(kernel: Kernel, parallel: bool, a: &mut [f64], b: &mut [f64], c: &mut [f64]) {
+        if parallel {
+            match kernel {
+                Kernel::Copy => c.par_iter_mut().zip(a.par_iter()).for_each(|(c, a)| *c = *a),
+                Kernel::Scale => b.par_iter_mut().zip(c.par_iter()).for_each(|(b, c)| *b = SCALAR * *c),
+                Kernel::Add => c.par_iter_mut().zip(a.par_iter()).zip(b.par_iter()).for_each(|((c, a), b)| *c = *a + *b),
+                Kernel::Triad => a.par_iter_mut().zip(b.par_iter()).zip(c.par_iter()).for_each(|((a, b), c)| *a = *b + SCALAR * *c),
+            }
+        } else {
+            match kernel {
+                Kernel::Copy => c.iter_mut().zip(a.iter()).for_each(|(c, a)| *c = *a),
+                Kernel::Scale => b.iter_mut().zip(c.iter()).for_each(|(b, c)| *b = SCALAR * *c),
+                Kernel::Add => c.iter_mut().zip(a.iter()).zip(b.iter()).for_each(|((c, a), b)| *c = *a + *b),
+                Kernel::Triad => a.iter_mut().zip(b.iter()).zip(c.iter()).for_each(|((a, b), c)| *a = *b + SCALAR * *c),
+            }
+        }
+        black_box((a, b, c));
+    }
+
+    fn allocate(&self, value: f64) -> Vec<f64> {
+        let mut array = vec![0f64; self.array_size / size_of::<f64>()];
+        // Touch every page from the threads that later run the kernels,
+        // so pages are placed on their NUMA nodes.
+        if self.parallel {
+            array.par_iter_mut().for_each(|v| *v = value);
+        } else {
+            array.iter_mut().for_each(|v| *v = value);
+        }
+        array
+    }
+}
+
+impl Benchmark for MemoryBenchmark {
+    fn name(&self) -> String {
+        format!("memory_{}{}", self.kernel.name(), if self.parallel { "_multi" } else { "" })
+    }
+
+    fn category(&self) -> String {
+        if self.parallel { BenchmarkKind::MemoryMulti.name() } else { BenchmarkKind::Memory.name() }
+    }
+
+    fn parameters(&self) -> Parameters {
+        Parameters::from([
+            (String::from("array_size"), json!(self.array_size)),
+            (String::from("threads"), json!(self.threads())),
+        ])
+    }
+
+    fn unit(&self) -> Unit {
+        Unit::BytesPerSecond
+    }
+
+    fn description(&self) -> String {
+        format!("Memory {} on {} thread{} reached",
+                self.kernel.name(),
+                self.threads(),
+                if self.threads() == 1 { "" } else { "s" })
+    }
+
+    fn progress_message(&self) -> String {
+        format!("Running the STREAM {} kernel on arrays of size {} with {} threads",
+                self.kernel.name(),
+                DecimalBytes(self.array_size as u64),
+                self.threads())
+    }
+
+    fn setup(&mut self) -> Result<()> {
+        self.arrays = [self.allocate(1.0), self.allocate(2.0), self.allocate(0.0)];
+        Ok(())
+    }
+
+    fn one_iteration(&mut self) -> Result<Measurement> {
+        let [a, b, c] = &mut self.arrays;
+        let now = Instant::now();
+        Self::run_kernel(self.kernel, self.parallel, a, b, c);
+        let elapsed = now.elapsed();
+        Ok(Measurement::throughput((self.kernel.arrays_touched() * self.array_size) as f64, elapsed))
+    }
+
+    fn teardown(&mut self) -> Result<()> {
+        self.arrays = Default::default();
+        Ok(())
+    }
+}
@@ -0,0 +1,135 @@
+use std::hint::black_box;
+use std::mem::size_of;
+use std::time::{Duration, Instant};
+use color_eyre::eyre::Result;
+use console::Style;
+use indicatif::HumanBytes;
+use rand::Rng;
+use serde_json::json;
+use crate::benchmark::{Benchmark, BenchmarkKind, Measurement};
+use crate::results::{BenchmarkResult, Parameters, Unit};
+
+///Smallest working set walked, well inside any L1 data cache
+const MIN_WORKING_SET: usize = 4 * 1024;
+
+///Dependent loads timed per working set and iteration
+const ACCESSES: usize = 1 << 22;
+
+///One cache line, so every access touches a different line
+#[repr(align(64))]
+#[derive(Clone, Copy)]
+struct Node {
+    next: usize,
+}
+
+///Measures load-to-use latency by chasing pointers through a random cyclic permutation of `working_set` bytes.
+///One benchmark is registered for each working set from a few KB up to the maximum to expose cache and TLB boundaries.
+pub struct MemoryLatencyBenchmark {
+    working_set: usize,
+    nodes: Vec<Node>,
+}
+
+impl MemoryLatencyBenchmark {
+    pub fn new(working_set: usize) -> Self {
+        Self { working_set, nodes: Vec::new() }
+    }
+
+    ///Working sets doubling from `MIN_WORKING_SET` up to `max_working_set`
+    pub fn working_sets(max_working_set: u64) -> Vec<usize> {
+        let max_working_set = (max_working_set as usize).max(MIN_WORKING_SET);
+        let mut sizes = vec![MIN_WORKING_SET];
+        while sizes.last().unwrap() * 2 <= max_working_set {
+            sizes.push(sizes.last().unwrap() * 2);
+        }
+        sizes
+    }
+
+    ///Links the first `count` nodes into a single random cycle using Sattolo's algorithm
+    fn link(nodes: &mut [Node], count: usize) {
+        let mut order: Vec<usize> = (0..count).collect();
+        let mut rng = rand::rng();
+        for i in (1..count).rev() {
+            let j = rng.random_range(0..i);
+            order.swap(i, j);
+        }
+        for i in 0..count {
+            nodes[order[i]].next = order[(i + 1) % count];
+        }
+    }
+
+    fn chase(nodes: &[Node], accesses: usize) -> usize {
+        let mut index = 0;
+        for _ in 0..accesses {
+            index = nodes[index].next;
+        }
+        black_box(index)
+    }
+
+    ///Time taken by `ACCESSES` dependent loads on a freshly linked working set
+    fn walk(&mut self) -> Duration {
+        let count = self.nodes.len();
+        Self::link(&mut self.nodes, count);
+        Self::chase(&self.nodes, count.min(ACCESSES));
+
+        let now = Instant::now();
+        Self::chase(&self.nodes, ACCESSES);
+        now.elapsed()
+    }
+
+    ///Prints the latency of every working set as a curve, one line per working set
+    pub fn print_curve(results: &[BenchmarkResult]) {
+        let value_style = Style::new().bright().green().bold();
+        let slowest = results.iter().map(|result| result.average).fold(f64::MIN_POSITIVE, f64::max);
+
+        println!("Memory latency per working set:");
+        for result in results.iter().filter(|result| !result.measurements.is_empty()) {
+            let working_set = result.parameters["working_set"].as_u64().unwrap_or(0);
+            println!("    {:<26}{:<26}{}",
+                     format!("{}", HumanBytes(working_set)),
+                     value_style.apply_to(format!("{:.2} ns", result.average * 1_000_000_000.0)),
+                     "#".repeat((result.average / slowest * 40.0) as usize));
+        }
+    }
+}
+
+impl Benchmark for MemoryLatencyBenchmark {
+    fn name(&self) -> String {
+        String::from("memory_latency")
+    }
+
+    fn category(&self) -> String {
+        BenchmarkKind::MemoryLatency.name()
+    }
+
+    fn parameters(&self) -> Parameters {
+        Parameters::from([
+            (String::from("working_set"), json!(self.working_set)),
+        ])
+    }
+
+    fn unit(&self) -> Unit {
+        Unit::Seconds
+    }
+
+    fn description(&self) -> String {
+        format!("Memory access on a working set of {} took", HumanBytes(self.working_set as u64))
+    }
+
+    fn progress_message(&self) -> String {
+        format!("Chasing pointers through a working set of {}", HumanBytes(self.working_set as u64))
+    }
+
+    fn setup(&mut self) -> Result<()> {
+        self.nodes = vec![Node { next: 0 }; self.working_set / size_of::<Node>()];
+        Ok(())
+    }
+
+    fn one_iteration(&mut self) -> Result<Measurement> {
+        Ok(Measurement::time_per_operation(self.walk(), ACCESSES))
+    }
+
+    fn teardown(&mut self) -> Result<()> {
+        self.nodes = Vec::new();
+        Ok(())
+    }
+}
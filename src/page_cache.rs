@@ -0,0 +1,47 @@
+use serde::Serialize;
+
+///How the page cache was emptied before a read iteration, from the most to the least thorough
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "snake_case")]
+pub enum CacheEviction {
+    ///`sync` followed by writing 3 to `/proc/sys/vm/drop_caches`, drops the whole page cache. Needs root
+    DropCaches,
+    ///`posix_fadvise(POSIX_FADV_DONTNEED)` on the benchmark file, drops the clean pages of that file
+    Fadvise,
+    ///Nothing was evicted, reads may be served from memory if unbuffered I/O is ignored
+    None,
+}
+
+///Eviction of the benchmark files from the Linux page cache, so reads measure the storage even where `O_DIRECT` is not honoured
+pub struct PageCache;
+
+impl PageCache {
+    ///Evicts `path` from the page cache, dropping the whole cache when privileged
+    #[cfg(target_os = "linux")]
+    pub fn evict(path: &str) -> CacheEviction {
+        use std::fs::{self, File};
+        use std::os::fd::AsRawFd;
+
+        unsafe {
+            libc::sync();
+        }
+        if fs::write("/proc/sys/vm/drop_caches", "3").is_ok() {
+            return CacheEviction::DropCaches;
+        }
+
+        let Ok(file) = File::open(path) else {
+            return CacheEviction::None;
+        };
+        // Dirty pages are not dropped, they were flushed by the sync above
+        if unsafe { libc::posix_fadvise(file.as_raw_fd(), 0, 0, libc::POSIX_FADV_DONTNEED) } == 0 {
+            CacheEviction::Fadvise
+        } else {
+            CacheEviction::None
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn evict(_path: &str) -> CacheEviction {
+        CacheEviction::None
+    }
+}
@@ -0,0 +1,152 @@
+use std::sync::OnceLock;
+use color_eyre::eyre::{eyre, Result};
+use color_eyre::Section;
+
+///First 1000 decimals of PI
+const PI_DECIMALS: &str = concat!(
+    "1415926535897932384626433832795028841971693993751058209749445923078164062862089986280348253421170679",
+    "8214808651328230664709384460955058223172535940812848111745028410270193852110555964462294895493038196",
+    "4428810975665933446128475648233786783165271201909145648566923460348610454326648213393607260249141273",
+    "7245870066063155881748815209209628292540917153643678925903600113305305488204665213841469519415116094",
+    "3305727036575959195309218611738193261179310511854807446237996274956735188575272489122793818301194912",
+    "9833673362440656643086021394946395224737190702179860943702770539217176293176752384674818467669405132",
+    "0005681271452635608277857713427577896091736371787214684409012249534301465495853710507922796892589235",
+    "4201995611212902196086403441815981362977477130996051870721134999999837297804995105973173281609631859",
+    "5024459455346908302642522308253344685035261931188171010003137838752886587533208381420617177669147303",
+    "5982534904287554687311595628638823537875937519577818577805321712268066130019278766111959092164201989",
+);
+
+///FNV-1a hashes of the first decimals of PI, for prefixes longer than `PI_DECIMALS`
+const PI_DECIMAL_HASHES: [(usize, u64); 21] = [
+    (1500, 0x2e21cadc82c56072), (2000, 0xb2af22a2740a2021), (2500, 0x70508b11063d8ef0),
+    (3000, 0x636489fb5b20491d), (3500, 0x101f42c653c7421d), (4000, 0x72fcee30aa308ef5),
+    (4500, 0x3cbd5f1f396b4504), (5000, 0x61a68389275042c4), (5500, 0xf7560b1cdcd39c34),
+    (6000, 0xd630098150fe580e), (6500, 0x810157d365aa8095), (7000, 0xf9012f852b1a5678),
+    (7500, 0xcdd05a1c2bfce162), (8000, 0x181fddd1b690dd7d), (8500, 0xeae86eed8b0a3fc8),
+    (9000, 0x69f6540e4ec357bd), (9500, 0x1d722cfdd265b6b5), (10000, 0xec7b9ca0dd41a929),
+    (20000, 0xa4774aa17ff56a75), (50000, 0x58a778b8f9c30c78), (100000, 0x787173ef15c2f208),
+];
+
+const UNSTABLE_SUGGESTION: &str = "The CPU or memory of this machine may be unstable, \
+    e.g. from overclocking, overheating or a hardware fault";
+
+///Checks the decimals of every PI calculation against known-good references.
+///Decimals within the embedded prefix are compared digit by digit, the longest prefix with a known hash
+///is compared against that hash. Decimals beyond it are compared against the first calculation of the run.
+pub struct PiVerification {
+    precision: usize,
+    ///The series is cut off after just enough terms for `precision` decimals,
+    ///so the last decimal can be off by one and is not verified
+    verified_decimals: usize,
+    ///Length and hash of the longest known prefix within the verified decimals
+    known_prefix: Option<(usize, u64)>,
+    expected_hash: OnceLock<u64>,
+}
+
+impl PiVerification {
+    pub fn new(precision: usize) -> Self {
+        let verified_decimals = precision.saturating_sub(1);
+        let known_prefix = PI_DECIMAL_HASHES.iter()
+            .copied()
+            .take_while(|(length, _)| *length <= verified_decimals)
+            .last();
+        Self { precision, verified_decimals, known_prefix, expected_hash: OnceLock::new() }
+    }
+
+    ///Verifies the decimal representation of PI, e.g. `3.14159...`
+    pub fn verify(&self, pi: &str) -> Result<()> {
+        let decimals = pi.strip_prefix("3.").unwrap_or_default();
+        if decimals.len() < self.precision {
+            return Err(eyre!("PI calculation with precision {} returned only {} decimals", self.precision, decimals.len())
+                .suggestion(UNSTABLE_SUGGESTION));
+        }
+        let decimals = &decimals.as_bytes()[..self.verified_decimals];
+
+        if let Some(position) = decimals.iter().zip(PI_DECIMALS.as_bytes()).position(|(actual, expected)| actual != expected) {
+            return Err(eyre!("PI calculation returned {} instead of {} at decimal place {}",
+                             decimals[position] as char,
+                             PI_DECIMALS.as_bytes()[position] as char,
+                             position + 1)
+                .suggestion(UNSTABLE_SUGGESTION));
+        }
+
+        let known_decimals = match self.known_prefix {
+            Some((length, hash)) => {
+                if Self::hash(&decimals[..length]) != hash {
+                    return Err(eyre!("PI calculation with precision {} returned wrong decimals between decimal place {} and {}",
+                                     self.precision,
+                                     PI_DECIMALS.len() + 1,
+                                     length)
+                        .suggestion(UNSTABLE_SUGGESTION));
+                }
+                length
+            }
+            None => PI_DECIMALS.len(),
+        };
+
+        if self.verified_decimals > known_decimals {
+            let hash = Self::hash(decimals);
+            if hash != *self.expected_hash.get_or_init(|| hash) {
+                return Err(eyre!("PI calculation with precision {} returned wrong decimals beyond decimal place {}",
+                                 self.precision,
+                                 known_decimals)
+                    .suggestion(UNSTABLE_SUGGESTION));
+            }
+        }
+
+        Ok(())
+    }
+
+    ///64 bit FNV-1a
+    fn hash(bytes: &[u8]) -> u64 {
+        bytes.iter().fold(0xcbf29ce484222325, |hash, byte| (hash ^ *byte as u64).wrapping_mul(0x100000001b3))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn known_prefix_is_the_longest_within_the_verified_decimals() {
+        assert_eq!(PiVerification::new(1000).known_prefix, None);
+        assert_eq!(PiVerification::new(1500).known_prefix, None);
+        assert_eq!(PiVerification::new(1501).known_prefix.map(|(length, _)| length), Some(1500));
+        assert_eq!(PiVerification::new(3000).known_prefix.map(|(length, _)| length), Some(2500));
+        assert_eq!(PiVerification::new(12345).known_prefix.map(|(length, _)| length), Some(10000));
+        assert_eq!(PiVerification::new(1_000_000).known_prefix.map(|(length, _)| length), Some(100000));
+    }
+
+    #[test]
+    fn verifies_decimals_within_the_embedded_prefix() {
+        let verification = PiVerification::new(1000);
+        assert!(verification.verify(&format!("3.{}", PI_DECIMALS)).is_ok());
+        assert!(verification.verify(&format!("3.{}", &PI_DECIMALS[..999])).is_err());
+        assert!(verification.verify(&format!("3.14159265358{}", &PI_DECIMALS[11..])).is_ok());
+        assert!(verification.verify(&format!("3.14159265359{}", &PI_DECIMALS[11..])).is_err());
+    }
+
+    #[test]
+    fn rejects_wrong_decimals_within_a_known_prefix() {
+        // Consistently wrong decimals beyond the embedded prefix are caught by the known hash
+        let pi = format!("3.{}{}", PI_DECIMALS, "0".repeat(1000));
+        let verification = PiVerification::new(2000);
+        assert!(verification.verify(&pi).is_err());
+        assert!(verification.verify(&pi).is_err());
+    }
+
+    #[test]
+    fn compares_decimals_beyond_the_known_prefixes_with_the_first_calculation() {
+        let verification = PiVerification::new(1200);
+        let first = format!("3.{}{}", PI_DECIMALS, "1".repeat(200));
+        assert!(verification.verify(&first).is_ok());
+        assert!(verification.verify(&first).is_ok());
+        assert!(verification.verify(&format!("3.{}{}", PI_DECIMALS, "2".repeat(200))).is_err());
+    }
+
+    #[test]
+    fn hash_is_64_bit_fnv_1a() {
+        assert_eq!(PiVerification::hash(b""), 0xcbf29ce484222325);
+        assert_eq!(PiVerification::hash(b"a"), 0xaf63dc4c8601ec8c);
+    }
+}
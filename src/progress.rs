@@ -0,0 +1,32 @@
+use std::time::Duration;
+use indicatif::{ProgressBar, ProgressStyle};
+
+///Progress bar of a running benchmark, drawn on the terminal
+pub struct Progress {
+    bar: ProgressBar,
+}
+
+impl Progress {
+    pub fn new(len: u64, message: String) -> Self {
+        let bar = ProgressBar::new(len).with_message(message);
+        bar.set_style(ProgressStyle::with_template("{msg} [{elapsed}]\n{wide_bar:.cyan/blue} {pos}/{len}")
+            .unwrap()
+            .progress_chars("##-"));
+        bar.enable_steady_tick(Duration::from_secs(1));
+        bar.inc(0);
+
+        Self { bar }
+    }
+
+    ///Counts a completed iteration, growing the total when a time bounded benchmark runs more iterations than expected
+    pub fn inc(&self) {
+        if self.bar.position() >= self.bar.length().unwrap_or(0) {
+            self.bar.inc_length(1);
+        }
+        self.bar.inc(1);
+    }
+
+    pub fn finish(&self) {
+        self.bar.finish();
+    }
+}
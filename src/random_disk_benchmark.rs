@@ -0,0 +1,244 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+#[cfg(unix)]
+use std::os::unix::fs::FileExt;
+#[cfg(windows)]
+use std::os::windows::fs::FileExt;
+#[cfg(target_os = "macos")]
+use std::os::fd::AsRawFd;
+use std::sync::Barrier;
+use std::thread;
+use std::time::{Duration, Instant};
+use color_eyre::eyre::{eyre, Result, WrapErr};
+use indicatif::{DecimalBytes, HumanBytes};
+use rand::Rng;
+use serde_json::json;
+use crate::benchmark::{Benchmark, BenchmarkKind, Measurement};
+use crate::disk_benchmark::{Aligned, DiskBenchmark, OpenOptionsExt};
+use crate::interrupt::Interrupt;
+use crate::page_cache::{CacheEviction, PageCache};
+use crate::results::{BenchmarkResult, Parameters, Unit};
+
+///Issues block sized reads and writes at random offsets of the benchmark file
+///from several threads at once, each thread keeping one request outstanding.
+pub struct RandomDiskBenchmark {
+    path: String,
+    size: u64,
+    num_operations: u32,
+    block_size: usize,
+    read_percent: u32,
+    queue_depth: u32,
+    alignment_size: usize,
+    ///Least thorough page cache eviction applied before an iteration
+    cache_eviction: Option<CacheEviction>,
+    ///Whether the file was opened with unbuffered I/O, false when the file system rejected it
+    direct_io: Option<bool>,
+    interrupt: Interrupt,
+}
+
+impl RandomDiskBenchmark {
+    pub fn new(path: String,
+               size: u64,
+               num_operations: u32,
+               block_size: u64,
+               read_percent: u32,
+               queue_depth: u32) -> Result<Self> {
+        let a = DiskBenchmark::block_alignment(&path)?;
+        let bs = (block_size as usize).max(a);
+        let bs = bs - bs % a;
+        let s = size - size % bs as u64;
+
+        Ok(Self {
+            path: DiskBenchmark::benchmark_file_path(&path, "random.diskbenchmark"),
+            size: s,
+            num_operations,
+            block_size: bs,
+            read_percent: read_percent.min(100),
+            queue_depth: queue_depth.max(1),
+            alignment_size: a,
+            cache_eviction: None,
+            direct_io: None,
+            interrupt: Interrupt::new(),
+        })
+    }
+
+    ///Stops the operations early once `interrupt` is set
+    pub fn with_interrupt(mut self, interrupt: Interrupt) -> Self {
+        self.interrupt = interrupt;
+        self
+    }
+
+    ///Opens the benchmark file, returning whether it is unbuffered
+    fn open_file(&self) -> Result<(File, bool)> {
+        let (file, direct_io) = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .open_unbuffered(&self.path)
+            .wrap_err_with(|| format!("Unable to open the benchmark file {}", self.path))?;
+
+        #[cfg(target_os = "macos")]
+        unsafe {
+            libc::fcntl(file.as_raw_fd(), libc::F_NOCACHE, 1);
+        }
+
+        Ok((file, direct_io))
+    }
+
+    #[cfg(unix)]
+    fn read_block(file: &File, buffer: &mut [u8], offset: u64) -> io::Result<()> {
+        file.read_exact_at(buffer, offset)
+    }
+
+    #[cfg(windows)]
+    fn read_block(file: &File, buffer: &mut [u8], offset: u64) -> io::Result<()> {
+        file.seek_read(buffer, offset).map(|_| ())
+    }
+
+    #[cfg(unix)]
+    fn write_block(file: &File, buffer: &[u8], offset: u64) -> io::Result<()> {
+        file.write_all_at(buffer, offset)
+    }
+
+    #[cfg(windows)]
+    fn write_block(file: &File, buffer: &[u8], offset: u64) -> io::Result<()> {
+        file.seek_write(buffer, offset).map(|_| ())
+    }
+
+    ///Fills the benchmark file with random data so reads hit allocated blocks
+    fn prepare_file(&mut self) -> Result<()> {
+        let chunk_size = self.block_size * (1024 * 1024 / self.block_size).max(1);
+        let mut aligned = Aligned::new(chunk_size, self.alignment_size);
+        let random_bytes = aligned.array();
+        rand::rng().fill(random_bytes);
+
+        let (mut file, direct_io) = self.open_file()?;
+        self.direct_io = Some(direct_io);
+        let mut written = 0u64;
+        while written < self.size && !self.interrupt.is_set() {
+            let length = (chunk_size as u64).min(self.size - written) as usize;
+            file.write_all(&random_bytes[..length])
+                .wrap_err_with(|| format!("Unable to write to the benchmark file {}", self.path))?;
+            written += length as u64;
+        }
+        Ok(())
+    }
+
+    ///Runs one batch of operations and returns the elapsed time and the latency of every operation.
+    ///The clock starts once every thread has opened the file and allocated its buffer.
+    fn run_operations(&self) -> Result<(Duration, Vec<Duration>)> {
+        let num_blocks = self.size / self.block_size as u64;
+        let ready = Barrier::new(self.queue_depth as usize + 1);
+
+        thread::scope(|scope| {
+            let workers: Vec<_> = (0..self.queue_depth)
+                .map(|worker| {
+                    let num_operations = self.num_operations / self.queue_depth
+                        + u32::from(worker < self.num_operations % self.queue_depth);
+                    let ready = &ready;
+                    scope.spawn(move || {
+                        let file = self.open_file().map(|(file, _)| file);
+                        let mut aligned = Aligned::new(self.block_size, self.alignment_size);
+                        let buffer = aligned.array();
+                        let mut rng = rand::rng();
+                        rng.fill(&mut buffer[..]);
+                        // Every worker reaches the barrier, even when it failed to open the file
+                        ready.wait();
+                        let file = file?;
+
+                        let mut latencies = Vec::with_capacity(num_operations as usize);
+                        for _ in 0..num_operations {
+                            if self.interrupt.is_set() {
+                                break;
+                            }
+                            let offset = rng.random_range(0..num_blocks) * self.block_size as u64;
+                            let read = rng.random_range(0..100) < self.read_percent;
+                            let now = Instant::now();
+                            if read {
+                                Self::read_block(&file, buffer, offset)
+                                    .wrap_err_with(|| format!("Unable to read from the benchmark file {}", self.path))?;
+                            } else {
+                                Self::write_block(&file, buffer, offset)
+                                    .wrap_err_with(|| format!("Unable to write to the benchmark file {}", self.path))?;
+                            }
+                            latencies.push(now.elapsed());
+                        }
+                        Ok(latencies)
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            ready.wait();
+            let now = Instant::now();
+            let latencies = workers.into_iter()
+                .map(|worker| worker.join().unwrap_or_else(|_| Err(eyre!("A random I/O thread panicked"))))
+                .collect::<Result<Vec<Vec<Duration>>>>();
+            let elapsed = now.elapsed();
+            Ok((elapsed, latencies?.concat()))
+        })
+    }
+}
+
+impl Benchmark for RandomDiskBenchmark {
+    fn name(&self) -> String {
+        String::from("disk_random")
+    }
+
+    fn category(&self) -> String {
+        BenchmarkKind::DiskRandom.name()
+    }
+
+    fn parameters(&self) -> Parameters {
+        Parameters::from([
+            (String::from("file_size"), json!(self.size)),
+            (String::from("block_size"), json!(self.block_size)),
+            (String::from("read_percent"), json!(self.read_percent)),
+            (String::from("queue_depth"), json!(self.queue_depth)),
+            (String::from("num_operations"), json!(self.num_operations)),
+            (String::from("alignment"), json!(self.alignment_size)),
+        ])
+    }
+
+    fn unit(&self) -> Unit {
+        Unit::OperationsPerSecond
+    }
+
+    fn description(&self) -> String {
+        format!("Random {} I/O with {}% reads at queue depth {} reached",
+                HumanBytes(self.block_size as u64),
+                self.read_percent,
+                self.queue_depth)
+    }
+
+    fn progress_message(&self) -> String {
+        format!("Running {} random {} operations on {} of size {}",
+                self.num_operations,
+                HumanBytes(self.block_size as u64),
+                self.path,
+                DecimalBytes(self.size))
+    }
+
+    fn setup(&mut self) -> Result<()> {
+        DiskBenchmark::delete_benchmark_file(&self.path);
+        self.prepare_file()?;
+        self.cache_eviction = None;
+        Ok(())
+    }
+
+    fn one_iteration(&mut self) -> Result<Measurement> {
+        if self.read_percent > 0 {
+            self.cache_eviction = self.cache_eviction.max(Some(PageCache::evict(&self.path)));
+        }
+        let (elapsed, latencies) = self.run_operations()?;
+        Ok(Measurement::throughput(latencies.len() as f64, elapsed).with_latencies(latencies))
+    }
+
+    fn teardown(&mut self) -> Result<()> {
+        DiskBenchmark::delete_benchmark_file(&self.path);
+        Ok(())
+    }
+
+    fn finish(&self, result: BenchmarkResult, _previous: &[BenchmarkResult]) -> BenchmarkResult {
+        DiskBenchmark::with_file_system_details(result, self.cache_eviction, self.direct_io)
+    }
+}
@@ -0,0 +1,347 @@
+use std::time::Duration;
+use color_eyre::eyre::Result;
+use crate::benchmark::{Benchmark, BenchmarkKind};
+use crate::cpu_benchmark::CPUBenchmark;
+use crate::cpu_scaling_benchmark::CPUScalingBenchmark;
+use crate::cpu_thread_pool::CPUThreadPool;
+use crate::disk_benchmark::{DiskBenchmark, DiskOperation};
+use crate::interrupt::Interrupt;
+use crate::iterations::Iterations;
+use crate::memory_benchmark::{Kernel, MemoryBenchmark};
+use crate::memory_latency_benchmark::MemoryLatencyBenchmark;
+use crate::random_disk_benchmark::RandomDiskBenchmark;
+use crate::reporter::Reporter;
+use crate::results::{BenchmarkReport, BenchmarkResult};
+use crate::settings::Settings;
+use crate::statistics::{Histogram, Statistics};
+use crate::sync_disk_benchmark::SyncDiskBenchmark;
+
+///Prints the results of a whole category at once instead of one by one, e.g. as a table
+pub type Summary = fn(&[BenchmarkResult]);
+
+///Benchmarks of one category, run one after the other
+struct Group {
+    category: String,
+    benchmarks: Vec<Box<dyn Benchmark>>,
+    summary: Option<Summary>,
+}
+
+///Every benchmark known to the program, in the order they run
+pub struct Registry {
+    iterations: Iterations,
+    interrupt: Interrupt,
+    groups: Vec<Group>,
+}
+
+impl Registry {
+    pub fn new(iterations: Iterations) -> Self {
+        Self { iterations, interrupt: Interrupt::new(), groups: Vec::new() }
+    }
+
+    ///Stops running benchmarks once `interrupt` is set. Benchmarks polling it themselves stop within an iteration
+    pub fn with_interrupt(mut self, interrupt: Interrupt) -> Self {
+        self.interrupt = interrupt;
+        self
+    }
+
+    ///Registry holding the built-in benchmarks configured by `settings`
+    pub fn builtin(settings: &Settings) -> Result<Self> {
+        let interrupt = &settings.interrupt;
+        let mut registry = Self::new(settings.iterations).with_interrupt(interrupt.clone());
+        let time_bounded = settings.iterations.is_time_bounded();
+        let cpu_thread_pool = CPUThreadPool::new(settings.threads, settings.cpu_list.clone());
+
+        registry.register(Box::new(CPUBenchmark::new(BenchmarkKind::Cpu,
+                                                     settings.precision,
+                                                     1,
+                                                     cpu_thread_pool.clone(),
+                                                     1,
+                                                     time_bounded).with_interrupt(interrupt.clone())));
+        registry.register(Box::new(CPUBenchmark::new(BenchmarkKind::CpuMulti,
+                                                     settings.precision,
+                                                     settings.num_calculations,
+                                                     cpu_thread_pool.clone(),
+                                                     cpu_thread_pool.threads(),
+                                                     time_bounded).with_interrupt(interrupt.clone())));
+        for threads in CPUScalingBenchmark::thread_counts(cpu_thread_pool.threads()) {
+            registry.register(Box::new(CPUScalingBenchmark::new(settings.precision,
+                                                                settings.num_calculations,
+                                                                cpu_thread_pool.clone(),
+                                                                threads).with_interrupt(interrupt.clone())));
+        }
+        registry.register_summary(&BenchmarkKind::CpuScaling.name(), CPUScalingBenchmark::print_scaling);
+
+        for parallel in [false, true] {
+            for kernel in Kernel::ALL {
+                registry.register(Box::new(MemoryBenchmark::new(settings.memory_array_size, kernel, parallel)));
+            }
+        }
+
+        for working_set in MemoryLatencyBenchmark::working_sets(settings.latency_max_size) {
+            registry.register(Box::new(MemoryLatencyBenchmark::new(working_set)));
+        }
+        registry.register_summary(&BenchmarkKind::MemoryLatency.name(), MemoryLatencyBenchmark::print_curve);
+
+        for operation in [DiskOperation::Write, DiskOperation::Read] {
+            registry.register(Box::new(DiskBenchmark::new(settings.temp_file_directory.clone(),
+                                                          settings.file_size,
+                                                          settings.buffer_size,
+                                                          settings.sync_write,
+                                                          operation)?.with_interrupt(interrupt.clone())));
+        }
+
+        registry.register(Box::new(RandomDiskBenchmark::new(settings.temp_file_directory.clone(),
+                                                            settings.random_file_size,
+                                                            settings.random_operations,
+                                                            settings.block_size,
+                                                            settings.read_percent,
+                                                            settings.queue_depth)?.with_interrupt(interrupt.clone())));
+
+        registry.register(Box::new(SyncDiskBenchmark::new(settings.temp_file_directory.clone(),
+                                                          settings.sync_operations,
+                                                          settings.sync_record_size,
+                                                          settings.sync_method).with_interrupt(interrupt.clone())));
+
+        Ok(registry)
+    }
+
+    ///Adds a benchmark to the end of its category, categories run in the order they were first registered
+    pub fn register(&mut self, benchmark: Box<dyn Benchmark>) {
+        let category = benchmark.category();
+        match self.groups.iter_mut().find(|group| group.category == category) {
+            Some(group) => group.benchmarks.push(benchmark),
+            None => self.groups.push(Group { category, benchmarks: vec![benchmark], summary: None }),
+        }
+    }
+
+    ///Prints the results of `category` with `summary` instead of one by one
+    pub fn register_summary(&mut self, category: &str, summary: Summary) {
+        if let Some(group) = self.groups.iter_mut().find(|group| group.category == category) {
+            group.summary = Some(summary);
+        }
+    }
+
+    ///Runs the benchmarks of every category accepted by `is_selected` and adds their results to `report`,
+    ///passing the progress and the results to `reporter`. Stops early when interrupted.
+    ///A time budget applies to each category and is split evenly between its benchmarks.
+    ///A benchmark which fails gets its error attached to its result and ends its category, the other categories still run.
+    pub fn run(&mut self,
+               is_selected: impl Fn(&str) -> bool,
+               report: &mut BenchmarkReport,
+               reporter: &mut dyn Reporter) {
+        for group in self.groups.iter_mut().filter(|group| is_selected(&group.category)) {
+            let iterations = self.iterations.share(group.benchmarks.len());
+            let mut results: Vec<BenchmarkResult> = Vec::new();
+            for benchmark in group.benchmarks.iter_mut() {
+                if self.interrupt.is_set() {
+                    break;
+                }
+                let result = Self::run_benchmark(benchmark.as_mut(), iterations, &self.interrupt, &results, reporter);
+                let failed = result.error.is_some();
+                if result.measurements.is_empty() && !failed {
+                    break;
+                }
+                results.push(result);
+                if failed {
+                    break;
+                }
+            }
+
+            reporter.category_finished(&group.category, &results, group.summary);
+            results.into_iter().for_each(|result| report.add_result(result));
+
+            if self.interrupt.is_set() {
+                break;
+            }
+        }
+    }
+
+    ///Runs one benchmark. `teardown` runs whenever `setup` was called, also when either of them or an iteration failed.
+    fn run_benchmark(benchmark: &mut dyn Benchmark,
+                     iterations: Iterations,
+                     interrupt: &Interrupt,
+                     previous: &[BenchmarkResult],
+                     reporter: &mut dyn Reporter) -> BenchmarkResult {
+        reporter.benchmark_started(benchmark, iterations);
+
+        let (result, outcome) = match benchmark.setup() {
+            Ok(()) => Self::measure(benchmark, iterations, interrupt, reporter),
+            Err(e) => (Self::result(benchmark, Vec::new(), &[], &[], &[]), Err(e)),
+        };
+        let outcome = outcome.and(benchmark.teardown());
+
+        let mut result = benchmark.finish(result, previous);
+        if let Err(e) = outcome {
+            result = result.with_error(&e);
+        }
+        reporter.benchmark_finished(&result);
+        result
+    }
+
+    ///Result of the iterations completed before the first one which failed, together with its error
+    fn measure(benchmark: &mut dyn Benchmark,
+               iterations: Iterations,
+               interrupt: &Interrupt,
+               reporter: &mut dyn Reporter) -> (BenchmarkResult, Result<()>) {
+        let mut measurements = Vec::with_capacity(iterations.expected() as usize);
+        let mut durations = Vec::with_capacity(iterations.expected() as usize);
+        let mut warmup_durations = Vec::with_capacity(iterations.warmup as usize);
+        let mut latencies = Vec::new();
+        let mut outcome = Ok(());
+
+        for iteration in iterations.iter() {
+            let measurement = match benchmark.one_iteration() {
+                Ok(measurement) => measurement,
+                Err(e) => {
+                    outcome = Err(e);
+                    break;
+                }
+            };
+            if interrupt.is_set() {
+                break;
+            }
+            reporter.iteration_finished(benchmark, iteration, &measurement);
+            if iteration.warmup {
+                warmup_durations.push(measurement.elapsed);
+                continue;
+            }
+            measurements.push(measurement.value);
+            durations.push(measurement.elapsed);
+            latencies.extend(measurement.latencies.iter().map(Duration::as_secs_f64));
+        }
+
+        (Self::result(benchmark, measurements, &durations, &warmup_durations, &latencies), outcome)
+    }
+
+    fn result(benchmark: &dyn Benchmark,
+              measurements: Vec<f64>,
+              durations: &[Duration],
+              warmup_durations: &[Duration],
+              latencies: &[f64]) -> BenchmarkResult {
+        let result = BenchmarkResult::new(&benchmark.name(),
+                                          benchmark.description(),
+                                          benchmark.parameters(),
+                                          benchmark.unit(),
+                                          measurements)
+            .with_durations(durations)
+            .with_warmup_durations(warmup_durations);
+        if latencies.is_empty() {
+            return result;
+        }
+        result
+            .with_latency(Statistics::new(latencies))
+            .with_latency_histogram(Histogram::new(latencies, 0.000_001))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use color_eyre::eyre::eyre;
+    use crate::benchmark::Measurement;
+    use crate::iterations::IterationLimit;
+    use crate::reporter::QuietReporter;
+    use crate::results::{Parameters, SystemInfo, Unit};
+
+    ///Fails its setup or the iteration numbered `fail_at`, counting the warm-up, and counts its teardowns
+    struct Failing {
+        name: &'static str,
+        fail_setup: bool,
+        fail_at: u32,
+        iterations: u32,
+        teardowns: u32,
+    }
+
+    impl Failing {
+        fn new(name: &'static str, fail_setup: bool, fail_at: u32) -> Self {
+            Self { name, fail_setup, fail_at, iterations: 0, teardowns: 0 }
+        }
+    }
+
+    impl Benchmark for Failing {
+        fn name(&self) -> String {
+            self.name.to_string()
+        }
+
+        fn category(&self) -> String {
+            self.name.to_string()
+        }
+
+        fn parameters(&self) -> Parameters {
+            Parameters::new()
+        }
+
+        fn unit(&self) -> Unit {
+            Unit::Seconds
+        }
+
+        fn description(&self) -> String {
+            String::from("Failing took")
+        }
+
+        fn progress_message(&self) -> String {
+            String::from("Failing")
+        }
+
+        fn setup(&mut self) -> Result<()> {
+            if self.fail_setup {
+                return Err(eyre!("setup failed"));
+            }
+            Ok(())
+        }
+
+        fn one_iteration(&mut self) -> Result<Measurement> {
+            self.iterations += 1;
+            if self.iterations == self.fail_at {
+                return Err(eyre!("iteration {} failed", self.iterations));
+            }
+            Ok(Measurement::time(Duration::from_millis(1)))
+        }
+
+        fn teardown(&mut self) -> Result<()> {
+            self.teardowns += 1;
+            Ok(())
+        }
+    }
+
+    fn run(benchmark: &mut Failing) -> BenchmarkResult {
+        Registry::run_benchmark(benchmark,
+                                Iterations::new(IterationLimit::Count(3), 1),
+                                &Interrupt::new(),
+                                &[],
+                                &mut QuietReporter)
+    }
+
+    #[test]
+    fn keeps_the_iterations_completed_before_an_error() {
+        let mut benchmark = Failing::new("failing", false, 3);
+        let result = run(&mut benchmark);
+        assert_eq!(result.measurements.len(), 1);
+        assert_eq!(result.warmup_durations_ns.len(), 1);
+        assert_eq!(result.error.as_deref(), Some("iteration 3 failed"));
+        assert_eq!(benchmark.teardowns, 1);
+    }
+
+    #[test]
+    fn tears_down_when_setup_fails() {
+        let mut benchmark = Failing::new("failing", true, 0);
+        let result = run(&mut benchmark);
+        assert!(result.measurements.is_empty());
+        assert_eq!(result.error.as_deref(), Some("setup failed"));
+        assert_eq!(benchmark.teardowns, 1);
+    }
+
+    #[test]
+    fn runs_the_other_categories_after_a_failure() {
+        let mut registry = Registry::new(Iterations::new(IterationLimit::Count(2), 0));
+        registry.register(Box::new(Failing::new("first", false, 1)));
+        registry.register(Box::new(Failing::new("second", false, 0)));
+        let mut report = BenchmarkReport::new(SystemInfo::collect());
+        registry.run(|_| true, &mut report, &mut QuietReporter);
+
+        assert_eq!(report.failed().len(), 1);
+        assert_eq!(report.failed()[0].name, "first");
+        assert_eq!(report.results.len(), 2);
+        assert_eq!(report.results[1].measurements.len(), 2);
+    }
+}
@@ -0,0 +1,345 @@
+use std::io::Write;
+use std::time::Instant;
+use indicatif::HumanDuration;
+use serde::Serialize;
+use crate::benchmark::{Benchmark, Measurement};
+use crate::iterations::{Iteration, Iterations};
+use crate::progress::Progress;
+use crate::registry::Summary;
+use crate::results::{BenchmarkReport, BenchmarkResult, Parameters, SystemInfo, Unit};
+use crate::score::Score;
+
+///Receives the progress and the results of a run. Benchmarks never print, everything shown or logged goes through a reporter.
+///Every method does nothing by default.
+pub trait Reporter {
+    fn run_started(&mut self, _system: &SystemInfo) {}
+
+    fn benchmark_started(&mut self, _benchmark: &dyn Benchmark, _iterations: Iterations) {}
+
+    fn iteration_finished(&mut self, _benchmark: &dyn Benchmark, _iteration: Iteration, _measurement: &Measurement) {}
+
+    ///Called for every benchmark, also when it was interrupted before completing an iteration
+    fn benchmark_finished(&mut self, _result: &BenchmarkResult) {}
+
+    ///Called with the results of a category once all its benchmarks finished.
+    ///`summary` prints them together, e.g. as a table, when the category has one.
+    fn category_finished(&mut self, _category: &str, _results: &[BenchmarkResult], _summary: Option<Summary>) {}
+
+    fn score(&mut self, _score: &Score) {}
+
+    fn run_finished(&mut self, _report: &BenchmarkReport) {}
+}
+
+///Prints the results of every category, together with its summary if it has one
+fn print_results(results: &[BenchmarkResult], summary: Option<Summary>) {
+    match summary {
+        Some(summary) if !results.is_empty() => {
+            summary(results);
+            println!();
+        }
+        _ => results.iter().for_each(|result| {
+            result.print();
+            println!();
+        }),
+    }
+}
+
+fn print_run_finished(report: &BenchmarkReport) {
+    if report.interrupted {
+        println!("Benchmark interrupted!");
+    } else if !report.failed().is_empty() {
+        println!("Benchmark completed with errors!");
+    } else {
+        println!("Benchmark completed!");
+    }
+}
+
+///Styled results with a progress bar for the running benchmark, for interactive terminals
+pub struct TerminalReporter {
+    print_results: bool,
+    progress: Option<Progress>,
+}
+
+impl TerminalReporter {
+    ///`print_results` set to false only shows the progress, e.g. when the results are printed as JSON
+    pub fn new(print_results: bool) -> Self {
+        Self { print_results, progress: None }
+    }
+}
+
+impl Reporter for TerminalReporter {
+    fn run_started(&mut self, system: &SystemInfo) {
+        if self.print_results {
+            system.print();
+            println!();
+        }
+    }
+
+    fn benchmark_started(&mut self, benchmark: &dyn Benchmark, iterations: Iterations) {
+        self.progress = Some(Progress::new(iterations.expected_with_warmup() as u64,
+                                           format!("{} {}...", benchmark.progress_message(), iterations)));
+    }
+
+    fn iteration_finished(&mut self, _benchmark: &dyn Benchmark, _iteration: Iteration, _measurement: &Measurement) {
+        if let Some(progress) = &self.progress {
+            progress.inc();
+        }
+    }
+
+    fn benchmark_finished(&mut self, _result: &BenchmarkResult) {
+        if let Some(progress) = self.progress.take() {
+            progress.finish();
+        }
+    }
+
+    fn category_finished(&mut self, _category: &str, results: &[BenchmarkResult], summary: Option<Summary>) {
+        if self.print_results {
+            print_results(results, summary);
+        }
+    }
+
+    fn score(&mut self, score: &Score) {
+        if self.print_results {
+            score.print();
+            println!();
+        }
+    }
+
+    fn run_finished(&mut self, report: &BenchmarkReport) {
+        if self.print_results {
+            print_run_finished(report);
+        }
+    }
+}
+
+///Unstyled results, with the progress logged line by line to standard error. For log files and CI
+pub struct PlainReporter {
+    print_results: bool,
+    started: Instant,
+    completed: u32,
+    expected: u32,
+}
+
+impl PlainReporter {
+    ///`print_results` set to false only logs the progress, e.g. when the results are printed as JSON
+    pub fn new(print_results: bool) -> Self {
+        if print_results {
+            console::set_colors_enabled(false);
+        }
+        Self { print_results, started: Instant::now(), completed: 0, expected: 0 }
+    }
+}
+
+impl Reporter for PlainReporter {
+    fn run_started(&mut self, system: &SystemInfo) {
+        if self.print_results {
+            system.print();
+            println!();
+        }
+    }
+
+    fn benchmark_started(&mut self, benchmark: &dyn Benchmark, iterations: Iterations) {
+        eprintln!("{} {}...", benchmark.progress_message(), iterations);
+        self.started = Instant::now();
+        self.completed = 0;
+        self.expected = iterations.expected_with_warmup();
+    }
+
+    ///Grows the total when a time bounded benchmark runs more iterations than expected
+    fn iteration_finished(&mut self, _benchmark: &dyn Benchmark, iteration: Iteration, _measurement: &Measurement) {
+        self.completed += 1;
+        self.expected = self.expected.max(self.completed);
+        eprintln!("{} {}/{} completed [{}]",
+                  if iteration.warmup { "Warm-up iteration" } else { "Iteration" },
+                  self.completed,
+                  self.expected,
+                  HumanDuration(self.started.elapsed()));
+    }
+
+    fn benchmark_finished(&mut self, _result: &BenchmarkResult) {
+        eprintln!("Completed in {}", HumanDuration(self.started.elapsed()));
+    }
+
+    fn category_finished(&mut self, _category: &str, results: &[BenchmarkResult], summary: Option<Summary>) {
+        if self.print_results {
+            print_results(results, summary);
+        }
+    }
+
+    fn score(&mut self, score: &Score) {
+        if self.print_results {
+            score.print();
+            println!();
+        }
+    }
+
+    fn run_finished(&mut self, report: &BenchmarkReport) {
+        if self.print_results {
+            print_run_finished(report);
+        }
+    }
+}
+
+///Reports nothing, errors are still returned
+pub struct QuietReporter;
+
+impl Reporter for QuietReporter {}
+
+///Event of the JSON lines stream, tagged with its kind in the `event` field
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum Event<'a> {
+    RunStarted {
+        system: &'a SystemInfo,
+    },
+    BenchmarkStarted {
+        name: String,
+        parameters: Parameters,
+        unit: Unit,
+        expected_iterations: u32,
+        warmup_iterations: u32,
+    },
+    IterationFinished {
+        name: String,
+        parameters: Parameters,
+        warmup: bool,
+        iteration: u32,
+        elapsed_ns: u128,
+        value: f64,
+    },
+    BenchmarkFinished {
+        result: &'a BenchmarkResult,
+    },
+    Score {
+        score: &'a Score,
+    },
+    RunFinished {
+        interrupted: bool,
+    },
+}
+
+///Writes every event as one JSON document per line, so a run can feed a dashboard while it progresses
+pub struct JsonLinesReporter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> JsonLinesReporter<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    ///Write errors are ignored, a closed stream must not abort the benchmarks
+    fn emit(&mut self, event: Event) {
+        if let Ok(line) = serde_json::to_string(&event) {
+            let _ = writeln!(self.writer, "{}", line);
+            let _ = self.writer.flush();
+        }
+    }
+}
+
+impl<W: Write> Reporter for JsonLinesReporter<W> {
+    fn run_started(&mut self, system: &SystemInfo) {
+        self.emit(Event::RunStarted { system });
+    }
+
+    fn benchmark_started(&mut self, benchmark: &dyn Benchmark, iterations: Iterations) {
+        self.emit(Event::BenchmarkStarted {
+            name: benchmark.name(),
+            parameters: benchmark.parameters(),
+            unit: benchmark.unit(),
+            expected_iterations: iterations.expected(),
+            warmup_iterations: iterations.warmup,
+        });
+    }
+
+    fn iteration_finished(&mut self, benchmark: &dyn Benchmark, iteration: Iteration, measurement: &Measurement) {
+        self.emit(Event::IterationFinished {
+            name: benchmark.name(),
+            parameters: benchmark.parameters(),
+            warmup: iteration.warmup,
+            iteration: iteration.number,
+            elapsed_ns: measurement.elapsed.as_nanos(),
+            value: measurement.value,
+        });
+    }
+
+    fn benchmark_finished(&mut self, result: &BenchmarkResult) {
+        self.emit(Event::BenchmarkFinished { result });
+    }
+
+    fn score(&mut self, score: &Score) {
+        self.emit(Event::Score { score });
+    }
+
+    fn run_finished(&mut self, report: &BenchmarkReport) {
+        self.emit(Event::RunFinished { interrupted: report.interrupted });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use color_eyre::eyre::Result;
+    use serde_json::Value;
+    use super::*;
+    use crate::iterations::IterationLimit;
+    use crate::registry::Registry;
+
+    struct Stub;
+
+    impl Benchmark for Stub {
+        fn name(&self) -> String {
+            String::from("stub")
+        }
+
+        fn category(&self) -> String {
+            String::from("stub")
+        }
+
+        fn parameters(&self) -> Parameters {
+            Parameters::new()
+        }
+
+        fn unit(&self) -> Unit {
+            Unit::Seconds
+        }
+
+        fn description(&self) -> String {
+            String::from("Stub took")
+        }
+
+        fn progress_message(&self) -> String {
+            String::from("Running the stub")
+        }
+
+        fn one_iteration(&mut self) -> Result<Measurement> {
+            Ok(Measurement::time(Duration::from_millis(1)))
+        }
+    }
+
+    #[test]
+    fn json_lines_stream_every_event_of_a_run() {
+        let mut output = Vec::new();
+        let mut reporter = JsonLinesReporter::new(&mut output);
+        let mut registry = Registry::new(Iterations::new(IterationLimit::Count(1), 1));
+        registry.register(Box::new(Stub));
+        let mut report = BenchmarkReport::new(SystemInfo::collect());
+
+        reporter.run_started(&report.system);
+        registry.run(|_| true, &mut report, &mut reporter);
+        reporter.run_finished(&report);
+
+        let events: Vec<Value> = String::from_utf8(output).unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        let kinds: Vec<&str> = events.iter().map(|event| event["event"].as_str().unwrap()).collect();
+        assert_eq!(kinds, ["run_started", "benchmark_started", "iteration_finished", "iteration_finished",
+                           "benchmark_finished", "run_finished"]);
+        assert_eq!(events[2]["warmup"], true);
+        assert_eq!(events[3]["warmup"], false);
+        assert_eq!(events[3]["iteration"], 1);
+        assert_eq!(events[4]["result"]["name"], "stub");
+        assert_eq!(events[5]["interrupted"], false);
+    }
+}
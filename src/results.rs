@@ -0,0 +1,345 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::time::Duration;
+use clap::ValueEnum;
+use color_eyre::eyre::{Report, Result, WrapErr};
+use console::Style;
+use indicatif::{DecimalBytes, HumanCount, HumanDuration};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sysinfo::System;
+use crate::score::Score;
+use crate::statistics::{Histogram, Statistics};
+
+///Benchmark parameters keyed by name, e.g. `precision` or `buffer_size`
+pub type Parameters = BTreeMap<String, Value>;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SystemInfo {
+    #[serde(default)]
+    pub host_name: String,
+    pub name: String,
+    pub kernel_version: String,
+    pub os_version: String,
+    #[serde(default)]
+    pub cpu_brand: String,
+    pub cpu_threads: usize,
+    pub available_memory: u64,
+    pub total_memory: u64,
+}
+
+impl SystemInfo {
+    pub fn collect() -> Self {
+        let mut sys = System::new_all();
+        sys.refresh_all();
+
+        Self {
+            host_name: System::host_name().unwrap_or(String::from("Unknown")),
+            name: System::name().unwrap_or(String::from("Unknown")),
+            kernel_version: System::kernel_version().unwrap_or(String::from("Unknown")),
+            os_version: System::long_os_version().unwrap_or(String::from("Unknown")),
+            cpu_brand: sys.cpus().first().map(|cpu| cpu.brand().trim().to_string()).unwrap_or(String::from("Unknown")),
+            cpu_threads: sys.cpus().len(),
+            available_memory: sys.available_memory(),
+            total_memory: sys.total_memory(),
+        }
+    }
+
+    ///Identifies the hardware and operating system a run was made on.
+    ///Changes whenever the host is resized, moved to other hardware or gets a new kernel.
+    pub fn fingerprint(&self) -> String {
+        format!("{}/{}/{} threads/{}/{}",
+                self.host_name,
+                self.cpu_brand,
+                self.cpu_threads,
+                DecimalBytes(self.total_memory),
+                self.kernel_version)
+    }
+
+    pub fn print(&self) {
+        let system_info_style = Style::new().bright().green().bold();
+        println!("{:<30}{:<10}", "Host name:", system_info_style.apply_to(&self.host_name));
+        println!("{:<30}{:<10}", "System name:", system_info_style.apply_to(&self.name));
+        println!("{:<30}{:<10}", "System kernel version:", system_info_style.apply_to(&self.kernel_version));
+        println!("{:<30}{:<10}", "System OS version:", system_info_style.apply_to(&self.os_version));
+        println!("{:<30}{:<10}", "CPU:", system_info_style.apply_to(&self.cpu_brand));
+        println!("{:<30}{:<10}", "Number of CPU threads:", system_info_style.apply_to(self.cpu_threads));
+        println!("{:<30}{:<10}", "Available memory:", system_info_style.apply_to(format!("{}/{}",
+                                                                                            DecimalBytes(self.available_memory),
+                                                                                            DecimalBytes(self.total_memory))));
+    }
+}
+
+///Unit of the per-iteration measurements of a benchmark
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Unit {
+    Seconds,
+    BytesPerSecond,
+    OperationsPerSecond,
+    FlushesPerSecond,
+    CalculationsPerSecond,
+}
+
+impl Unit {
+    pub fn format(&self, value: f64) -> String {
+        match self {
+            Unit::Seconds if value < 0.000_001 => format!("{:.2} nanoseconds", value * 1_000_000_000.0),
+            Unit::Seconds if value < 0.001 => format!("{:.2} microseconds", value * 1_000_000.0),
+            Unit::Seconds if value < 1.0 => format!("{:.2} milliseconds", value * 1000.0),
+            Unit::Seconds => format!("{}", HumanDuration(Duration::from_secs_f64(value))),
+            Unit::BytesPerSecond => format!("{}/s", DecimalBytes(value as u64)),
+            Unit::OperationsPerSecond => format!("{} IOPS", HumanCount(value as u64)),
+            Unit::FlushesPerSecond => format!("{} flushes/s", HumanCount(value as u64)),
+            Unit::CalculationsPerSecond => format!("{:.2} calculations/s", value),
+        }
+    }
+
+    pub fn higher_is_better(&self) -> bool {
+        match self {
+            Unit::Seconds => false,
+            Unit::BytesPerSecond
+            | Unit::OperationsPerSecond
+            | Unit::FlushesPerSecond
+            | Unit::CalculationsPerSecond => true,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BenchmarkResult {
+    pub name: String,
+    pub description: String,
+    pub parameters: Parameters,
+    pub unit: Unit,
+    pub measurements: Vec<f64>,
+    pub average: f64,
+    #[serde(default)]
+    pub statistics: Statistics,
+    ///Wall-clock duration of every iteration in nanoseconds
+    #[serde(default)]
+    pub durations_ns: Vec<u128>,
+    ///Wall-clock duration of every warm-up iteration in nanoseconds, excluded from the statistics
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warmup_durations_ns: Vec<u128>,
+    ///Summary of the latency of individual operations in seconds, for benchmarks issuing many small requests
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency: Option<Statistics>,
+    ///Distribution of the latency of individual operations, bucket bounds in seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub latency_histogram: Option<Histogram>,
+    ///Values derived from the measurements which only make sense for this benchmark
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub details: BTreeMap<String, Value>,
+    ///Error which stopped the benchmark, the measurements only hold the iterations completed before it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl BenchmarkResult {
+    pub fn new(name: &str, description: String, parameters: Parameters, unit: Unit, measurements: Vec<f64>) -> Self {
+        let statistics = Statistics::new(&measurements);
+        Self {
+            name: name.to_string(),
+            description,
+            parameters,
+            unit,
+            measurements,
+            average: statistics.mean,
+            statistics,
+            durations_ns: Vec::new(),
+            warmup_durations_ns: Vec::new(),
+            latency: None,
+            latency_histogram: None,
+            details: BTreeMap::new(),
+            error: None,
+        }
+    }
+
+    pub fn with_durations(mut self, durations: &[Duration]) -> Self {
+        self.durations_ns = durations.iter().map(Duration::as_nanos).collect();
+        self
+    }
+
+    pub fn with_warmup_durations(mut self, durations: &[Duration]) -> Self {
+        self.warmup_durations_ns = durations.iter().map(Duration::as_nanos).collect();
+        self
+    }
+
+    pub fn with_detail(mut self, key: &str, value: Value) -> Self {
+        self.details.insert(key.to_string(), value);
+        self
+    }
+
+    pub fn with_latency(mut self, latency: Statistics) -> Self {
+        self.latency = Some(latency);
+        self
+    }
+
+    pub fn with_latency_histogram(mut self, histogram: Histogram) -> Self {
+        self.latency_histogram = Some(histogram);
+        self
+    }
+
+    pub fn with_error(mut self, error: &Report) -> Self {
+        self.error = Some(format!("{:#}", error));
+        self
+    }
+
+    ///Benchmark name followed by its parameters, e.g. `cpu_multi(num_calculations=20, precision=3000)`
+    pub fn label(&self) -> String {
+        let parameters = self.parameters.iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!("{}({})", self.name, parameters)
+    }
+
+    ///Whether both results were produced by the same benchmark with the same workload and are measured in the same unit.
+    ///Parameters which describe the machine rather than the workload are ignored, so runs on different machines match.
+    pub fn matches(&self, other: &BenchmarkResult) -> bool {
+        self.name == other.name && self.unit == other.unit && self.workload() == other.workload()
+    }
+
+    fn workload(&self) -> Vec<(&String, &serde_json::Value)> {
+        self.parameters.iter()
+            .filter(|(key, _)| !self.is_machine_parameter(key))
+            .collect()
+    }
+
+    ///Whether the parameter `key` is set by the machine the benchmark ran on instead of by its arguments
+    fn is_machine_parameter(&self, key: &str) -> bool {
+        match key {
+            "alignment" => true,
+            // Every point of the scaling curve runs on a fixed number of threads
+            "threads" => self.name != "cpu_scaling",
+            _ => false,
+        }
+    }
+
+    pub fn print(&self) {
+        if let Some(error) = &self.error {
+            println!("{} {}", Style::new().bright().red().bold().apply_to(format!("{} failed:", self.name)), error);
+            if self.measurements.is_empty() {
+                return;
+            }
+        }
+
+        let value_style = Style::new().bright().green().bold().underlined();
+        println!("{} {} on average.",
+                 self.description,
+                 value_style.apply_to(self.unit.format(self.average)));
+
+        let statistics = &self.statistics;
+        println!("{:<30}{}", "Median:", self.unit.format(statistics.median));
+        println!("{:<30}{} ({:.2}%)", "Standard deviation (CV):", self.unit.format(statistics.std_dev), statistics.coefficient_of_variation);
+        println!("{:<30}{} - {}", "Min - Max:", self.unit.format(statistics.min), self.unit.format(statistics.max));
+        println!("{:<30}{} / {}", "P95 / P99:", self.unit.format(statistics.p95), self.unit.format(statistics.p99));
+        println!("{:<30}{} - {}", "95% confidence interval:",
+                 self.unit.format(statistics.confidence_interval.0),
+                 self.unit.format(statistics.confidence_interval.1));
+
+        if !self.warmup_durations_ns.is_empty() {
+            let warmup_durations = self.warmup_durations_ns.iter()
+                .map(|ns| Unit::Seconds.format(*ns as f64 / 1_000_000_000.0))
+                .collect::<Vec<_>>()
+                .join(", ");
+            println!("{:<30}{}", "Warm-up (excluded):", warmup_durations);
+        }
+
+        if let Some(cache_eviction) = self.details.get("cache_eviction").and_then(Value::as_str) {
+            println!("{:<30}{}", "Page cache eviction:", cache_eviction);
+        }
+        if let Some(direct_io) = self.details.get("direct_io").and_then(Value::as_bool) {
+            println!("{:<30}{}", "Direct I/O:", if direct_io { "yes" } else { "no, rejected by the file system" });
+        }
+
+        if let Some(latency) = &self.latency {
+            println!("{:<30}{} / {} / {}", "Latency median / P95 / P99:",
+                     Unit::Seconds.format(latency.median),
+                     Unit::Seconds.format(latency.p95),
+                     Unit::Seconds.format(latency.p99));
+            println!("{:<30}{} - {}", "Latency min - max:",
+                     Unit::Seconds.format(latency.min),
+                     Unit::Seconds.format(latency.max));
+        }
+
+        if let Some(histogram) = &self.latency_histogram {
+            let total = histogram.buckets.iter().map(|bucket| bucket.count).sum::<usize>().max(1);
+            println!("Latency histogram:");
+            for bucket in histogram.buckets.iter().filter(|bucket| bucket.count > 0) {
+                println!("    {:<26}{:<10}{}",
+                         format!("<= {}", Unit::Seconds.format(bucket.upper_bound)),
+                         bucket.count,
+                         "#".repeat(bucket.count * 40 / total));
+            }
+        }
+    }
+}
+
+///Document holding everything a run produced, used for the JSON output
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct BenchmarkReport {
+    pub version: String,
+    pub system: SystemInfo,
+    pub results: Vec<BenchmarkResult>,
+    ///Whether the run was interrupted and only holds the iterations completed before that
+    #[serde(default)]
+    pub interrupted: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score: Option<Score>,
+}
+
+impl BenchmarkReport {
+    pub fn new(system: SystemInfo) -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            system,
+            results: Vec::new(),
+            interrupted: false,
+            score: None,
+        }
+    }
+
+    ///Adds the result of a finished benchmark.
+    ///Results of benchmarks interrupted before completing a single iteration are dropped, failed ones are kept.
+    pub fn add_result(&mut self, result: BenchmarkResult) {
+        if result.measurements.is_empty() && result.error.is_none() {
+            return;
+        }
+        self.results.push(result);
+    }
+
+    ///Results of the benchmarks which stopped with an error
+    pub fn failed(&self) -> Vec<&BenchmarkResult> {
+        self.results.iter().filter(|result| result.error.is_some()).collect()
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).wrap_err("Unable to serialize benchmark results")
+    }
+
+    pub fn load(path: &str) -> Result<Self> {
+        let content = fs::read_to_string(path).wrap_err_with(|| format!("Unable to read results from {}", path))?;
+        serde_json::from_str(&content).wrap_err_with(|| format!("{} is not a valid benchmark results file", path))
+    }
+
+    pub fn save(&self, path: &str) -> Result<()> {
+        fs::write(path, self.to_json()?).wrap_err_with(|| format!("Unable to write results to {}", path))
+    }
+}
+
+///Format used to print the benchmark results
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat {
+    ///Human readable styled text with progress bars. Falls back to plain when standard output is not a terminal
+    Text,
+    ///Unstyled text, with the progress logged line by line to standard error
+    Plain,
+    ///Nothing but errors
+    Quiet,
+    ///Single JSON document containing the system information and every measurement
+    Json,
+    ///One JSON event per line as the run progresses: benchmark started, iteration finished, benchmark finished
+    JsonLines,
+}
@@ -0,0 +1,156 @@
+use std::fmt;
+use console::Style;
+use serde::{Deserialize, Serialize};
+use color_eyre::eyre::Result;
+use crate::compare::RunComparison;
+use crate::results::{BenchmarkReport, BenchmarkResult};
+
+///Score of a result equal to the reference machine
+const REFERENCE_SCORE: f64 = 1000.0;
+
+///Group of benchmarks summarised by one score
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Category {
+    CpuSingle,
+    CpuMulti,
+    Memory,
+    Disk,
+}
+
+impl Category {
+    const ALL: [Category; 4] = [Category::CpuSingle, Category::CpuMulti, Category::Memory, Category::Disk];
+
+    ///Category of a benchmark result. The CPU scaling curve is left out, its throughput depends on the thread count.
+    fn of(result: &BenchmarkResult) -> Option<Category> {
+        match result.name.as_str() {
+            "cpu_single" => Some(Category::CpuSingle),
+            "cpu_multi" => Some(Category::CpuMulti),
+            name if name.starts_with("memory_") => Some(Category::Memory),
+            name if name.starts_with("disk_") => Some(Category::Disk),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Category::CpuSingle => write!(f, "CPU single core"),
+            Category::CpuMulti => write!(f, "CPU multi core"),
+            Category::Memory => write!(f, "Memory"),
+            Category::Disk => write!(f, "Disk"),
+        }
+    }
+}
+
+///Average results of a reference machine which all scores are relative to
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ReferenceProfile {
+    pub name: String,
+    pub results: Vec<BenchmarkResult>,
+}
+
+impl ReferenceProfile {
+    ///Results of a run on a reference machine saved with `--output-file`
+    pub fn load(path: &str) -> Result<Self> {
+        let report = BenchmarkReport::load(path)?;
+        Ok(Self {
+            name: format!("reference {} from {}", path, report.system.host_name),
+            results: report.results,
+        })
+    }
+
+    ///Averages of a run with the default arguments on a 1 vCPU Xeon virtual machine with 6 GB of memory
+    pub fn builtin() -> Self {
+        serde_json::from_str(include_str!("reference.json")).expect("The built-in reference profile is valid")
+    }
+
+    ///Reference result of the same workload, machines with more cores score higher on the multi core benchmarks
+    fn find(&self, result: &BenchmarkResult) -> Option<&BenchmarkResult> {
+        self.results.iter().find(|reference| reference.matches(result))
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct CategoryScore {
+    pub category: Category,
+    pub score: f64,
+    ///Number of benchmarks combined into the score
+    pub benchmarks: usize,
+}
+
+///Results converted into scores relative to a reference machine scoring 1000.
+///Category scores are the geometric mean of their benchmarks, the overall score the geometric mean of the categories.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Score {
+    pub reference: String,
+    pub overall: f64,
+    pub categories: Vec<CategoryScore>,
+    ///Labels of results without a reference result
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub unscored: Vec<String>,
+}
+
+impl Score {
+    ///Scores `results` against `reference`, or `None` when no result has a reference result
+    pub fn new(results: &[BenchmarkResult], reference: &ReferenceProfile) -> Option<Self> {
+        let mut unscored = Vec::new();
+        let mut scores: Vec<(Category, f64)> = Vec::new();
+        for result in results.iter().filter(|result| !result.measurements.is_empty()) {
+            let Some(category) = Category::of(result) else {
+                continue;
+            };
+            match reference.find(result) {
+                Some(reference) => scores.push((category, REFERENCE_SCORE * RunComparison::ratio(reference, result))),
+                None => unscored.push(result.label()),
+            }
+        }
+
+        let categories: Vec<CategoryScore> = Category::ALL.iter()
+            .map(|category| {
+                let category_scores: Vec<f64> = scores.iter()
+                    .filter(|(c, _)| c == category)
+                    .map(|(_, score)| *score)
+                    .collect();
+                CategoryScore {
+                    category: *category,
+                    score: Self::geometric_mean(&category_scores),
+                    benchmarks: category_scores.len(),
+                }
+            })
+            .filter(|category| category.benchmarks > 0)
+            .collect();
+        if categories.is_empty() {
+            return None;
+        }
+
+        let category_scores: Vec<f64> = categories.iter().map(|category| category.score).collect();
+        Some(Self {
+            reference: reference.name.clone(),
+            overall: Self::geometric_mean(&category_scores),
+            categories,
+            unscored,
+        })
+    }
+
+    fn geometric_mean(values: &[f64]) -> f64 {
+        (values.iter().map(|value| value.ln()).sum::<f64>() / values.len() as f64).exp()
+    }
+
+    pub fn print(&self) {
+        let value_style = Style::new().bright().green().bold();
+        println!("Score against the {} ({:.0} is as fast as the reference):", self.reference, REFERENCE_SCORE);
+        for category in &self.categories {
+            println!("    {:<26}{:<10}from {} benchmark{}",
+                     category.category.to_string(),
+                     value_style.apply_to(format!("{:.0}", category.score)),
+                     category.benchmarks,
+                     if category.benchmarks == 1 { "" } else { "s" });
+        }
+        println!("    {:<26}{}", "Overall:", value_style.clone().underlined().apply_to(format!("{:.0}", self.overall)));
+        if !self.unscored.is_empty() {
+            println!("Not scored, the reference has no result with the same parameters: {}", self.unscored.join(", "));
+        }
+    }
+}
@@ -0,0 +1,222 @@
+use std::env;
+use std::fs::metadata;
+use std::path::Path;
+use color_eyre::eyre::{eyre, Result};
+use color_eyre::Section;
+use indicatif::DecimalBytes;
+use parse_size::parse_size;
+use sysinfo::{Disks, System};
+use crate::benchmark::BenchmarkKind;
+use crate::cpu_thread_pool::CPUThreadPool;
+use crate::disk_benchmark::DiskBenchmark;
+use crate::interrupt::Interrupt;
+use crate::iterations::{IterationLimit, Iterations};
+use crate::sync_disk_benchmark::SyncMethod;
+
+///Configuration of every built-in benchmark, with every size in bytes.
+///The defaults match the defaults of the command line.
+#[derive(Debug, Clone)]
+pub struct Settings {
+    pub num_calculations: u32,
+    pub iterations: Iterations,
+    pub precision: usize,
+    pub threads: Option<usize>,
+    pub cpu_list: Vec<usize>,
+    pub file_size: u64,
+    pub buffer_size: u64,
+    pub sync_write: bool,
+    pub random_file_size: u64,
+    pub block_size: u64,
+    pub read_percent: u32,
+    pub queue_depth: u32,
+    pub random_operations: u32,
+    pub sync_method: SyncMethod,
+    pub sync_record_size: u64,
+    pub sync_operations: u32,
+    pub memory_array_size: u64,
+    pub latency_max_size: u64,
+    pub temp_file_directory: String,
+    ///Stops the run when set, e.g. by Ctrl-C or by another thread
+    pub interrupt: Interrupt,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            num_calculations: 20,
+            iterations: Iterations::new(IterationLimit::Count(5), 1),
+            precision: 3000,
+            threads: None,
+            cpu_list: Vec::new(),
+            file_size: 4_000_000_000,
+            buffer_size: 100 * 1024 * 1024,
+            sync_write: false,
+            random_file_size: 1_000_000_000,
+            block_size: 4096,
+            read_percent: 70,
+            queue_depth: 32,
+            random_operations: 10000,
+            sync_method: SyncMethod::Data,
+            sync_record_size: 4096,
+            sync_operations: 1000,
+            memory_array_size: 128 * 1024 * 1024,
+            latency_max_size: 512 * 1024 * 1024,
+            temp_file_directory: env::temp_dir().to_string_lossy().into_owned(),
+            interrupt: Interrupt::new(),
+        }
+    }
+}
+
+impl Settings {
+    ///Checks that the benchmarks accepted by `is_selected` can run with these settings on this machine,
+    ///e.g. that there is enough memory and disk space
+    pub fn validate(&self, is_selected: impl Fn(BenchmarkKind) -> bool) -> Result<()> {
+        if self.num_calculations == 0 || self.precision == 0 || self.iterations.expected() == 0 {
+            return Err(eyre!("The number of calculations, the PI precision and the iterations must be greater than 0"));
+        }
+        if self.queue_depth == 0 || self.random_operations == 0 || self.sync_operations == 0 {
+            return Err(eyre!("The queue depth and the number of random and sync operations must be greater than 0"));
+        }
+        if self.read_percent > 100 {
+            return Err(eyre!("The read percentage {} is larger than 100", self.read_percent));
+        }
+        let sizes = [self.file_size, self.buffer_size, self.random_file_size, self.block_size,
+                     self.sync_record_size, self.memory_array_size, self.latency_max_size];
+        if sizes.contains(&0) {
+            return Err(eyre!("The file, buffer, block, record and memory sizes must be greater than 0"));
+        }
+        Self::directory("The temporary file directory", &self.temp_file_directory)?;
+
+        if is_selected(BenchmarkKind::Disk) || is_selected(BenchmarkKind::DiskRandom) {
+            self.check_disk_arguments(&is_selected)?;
+        }
+
+        if is_selected(BenchmarkKind::Memory)
+            || is_selected(BenchmarkKind::MemoryMulti)
+            || is_selected(BenchmarkKind::MemoryLatency) {
+            self.check_memory_arguments(&is_selected)?;
+        }
+
+        Ok(())
+    }
+
+    fn check_memory_arguments(&self, is_selected: &impl Fn(BenchmarkKind) -> bool) -> Result<()> {
+        let mut sys = System::new();
+        sys.refresh_memory();
+
+        if is_selected(BenchmarkKind::Memory) || is_selected(BenchmarkKind::MemoryMulti) {
+            let required_memory = self.memory_array_size.saturating_mul(3);
+            if required_memory > sys.available_memory() {
+                return Err(eyre!("--memory-array-size {} needs {} for three arrays but only {} of memory is available",
+                                 DecimalBytes(self.memory_array_size),
+                                 DecimalBytes(required_memory),
+                                 DecimalBytes(sys.available_memory())));
+            }
+        }
+
+        if is_selected(BenchmarkKind::MemoryLatency) {
+            // The random permutation used to link the nodes takes another word per 64 byte node
+            let required_memory = self.latency_max_size.saturating_add(self.latency_max_size / 8);
+            if required_memory > sys.available_memory() {
+                return Err(eyre!("--latency-max-size {} needs {} but only {} of memory is available",
+                                 DecimalBytes(self.latency_max_size),
+                                 DecimalBytes(required_memory),
+                                 DecimalBytes(sys.available_memory())));
+            }
+        }
+
+        Ok(())
+    }
+
+    ///Parses the `--cpu-list` argument
+    pub fn cpu_list(value: &str) -> Result<Vec<usize>> {
+        if !cfg!(target_os = "linux") {
+            return Err(eyre!("--cpu-list is only supported on Linux"));
+        }
+        CPUThreadPool::parse_cpu_list(value)
+    }
+
+    ///Checks that the argument `name` is not 0
+    pub fn positive(name: &str, value: u32) -> Result<u32> {
+        if value == 0 {
+            return Err(eyre!("{} must be greater than 0", name));
+        }
+        Ok(value)
+    }
+
+    ///Parses the size argument `name`, e.g. 4GB or 512MiB, into bytes
+    pub fn size(name: &str, value: &str) -> Result<u64> {
+        match parse_size(value) {
+            Ok(0) => Err(eyre!("{} must be greater than 0", name)),
+            Ok(size) => Ok(size),
+            Err(e) => Err(eyre!("{} '{}' is not a valid size: {}", name, value, e)
+                .suggestion("Use a number followed by an optional unit, e.g. 4GB, 512MiB or 4096")),
+        }
+    }
+
+    ///Checks that the directory argument `name` is an accessible directory
+    pub fn directory(name: &str, value: &str) -> Result<String> {
+        match metadata(value) {
+            Ok(m) if m.is_dir() => Ok(value.to_string()),
+            Ok(_) => Err(eyre!("{} '{}' is not a directory", name, value)),
+            Err(e) => Err(eyre!("{} '{}' is not accessible: {}", name, value, e)),
+        }
+    }
+
+    fn check_disk_arguments(&self, is_selected: &impl Fn(BenchmarkKind) -> bool) -> Result<()> {
+        let alignment = DiskBenchmark::block_alignment(&self.temp_file_directory)? as u64;
+        let mut required_space = 0;
+
+        if is_selected(BenchmarkKind::Disk) {
+            if self.buffer_size > self.file_size {
+                return Err(eyre!("--buffer-size {} is larger than --filesize {}",
+                                 DecimalBytes(self.buffer_size),
+                                 DecimalBytes(self.file_size)));
+            }
+            if !self.buffer_size.is_multiple_of(alignment) {
+                return Err(eyre!("--buffer-size {} is not a multiple of the file system block size of {} bytes",
+                                 self.buffer_size,
+                                 alignment)
+                    .suggestion("Unbuffered I/O requires aligned buffers, use a power of two unit such as 64MiB"));
+            }
+            required_space = self.file_size.div_ceil(self.buffer_size) * self.buffer_size;
+        }
+
+        if is_selected(BenchmarkKind::DiskRandom) {
+            if self.block_size > self.random_file_size {
+                return Err(eyre!("--block-size {} is larger than --random-filesize {}",
+                                 DecimalBytes(self.block_size),
+                                 DecimalBytes(self.random_file_size)));
+            }
+            if !self.block_size.is_multiple_of(alignment) {
+                return Err(eyre!("--block-size {} is not a multiple of the file system block size of {} bytes",
+                                 self.block_size,
+                                 alignment)
+                    .suggestion("Unbuffered I/O requires aligned blocks, use a power of two unit such as 4KiB"));
+            }
+            required_space = required_space.max(self.random_file_size);
+        }
+
+        if let Some(available_space) = Self::available_space(&self.temp_file_directory) {
+            if required_space > available_space {
+                return Err(eyre!("--temp-file-directory '{}' has {} available but the disk benchmarks need {}",
+                                 self.temp_file_directory,
+                                 DecimalBytes(available_space),
+                                 DecimalBytes(required_space))
+                    .suggestion("Use a smaller --filesize or --random-filesize, or another --temp-file-directory"));
+            }
+        }
+
+        Ok(())
+    }
+
+    ///Free space of the disk whose mount point contains `directory`
+    fn available_space(directory: &str) -> Option<u64> {
+        let path = Path::new(directory).canonicalize().ok()?;
+        let disks = Disks::new_with_refreshed_list();
+        disks.list().iter()
+            .filter(|disk| path.starts_with(disk.mount_point()))
+            .max_by_key(|disk| disk.mount_point().as_os_str().len())
+            .map(|disk| disk.available_space())
+    }
+}
@@ -0,0 +1,192 @@
+use serde::{Deserialize, Serialize};
+
+///Two-sided 95% critical values of Student's t-distribution for 1 to 30 degrees of freedom
+const T_95: [f64; 30] = [
+    12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228,
+    2.201, 2.179, 2.160, 2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086,
+    2.080, 2.074, 2.069, 2.064, 2.060, 2.056, 2.052, 2.048, 2.045, 2.042,
+];
+
+///Normal approximation used once there are more than 30 degrees of freedom
+const Z_95: f64 = 1.960;
+
+///Summary of a set of per-iteration measurements
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Statistics {
+    pub count: usize,
+    pub mean: f64,
+    pub median: f64,
+    pub std_dev: f64,
+    ///Standard deviation relative to the mean, in percent
+    pub coefficient_of_variation: f64,
+    pub min: f64,
+    pub max: f64,
+    pub p95: f64,
+    pub p99: f64,
+    ///Lower and upper bound of the 95% confidence interval of the mean
+    pub confidence_interval: (f64, f64),
+}
+
+impl Statistics {
+    pub fn new(measurements: &[f64]) -> Self {
+        if measurements.is_empty() {
+            return Self::default();
+        }
+
+        let mut sorted = measurements.to_vec();
+        sorted.sort_by(f64::total_cmp);
+
+        let count = sorted.len();
+        let mean = sorted.iter().sum::<f64>() / count as f64;
+        let std_dev = if count > 1 {
+            (sorted.iter().map(|m| (m - mean).powi(2)).sum::<f64>() / (count - 1) as f64).sqrt()
+        } else {
+            0.0
+        };
+        let coefficient_of_variation = if mean != 0.0 { std_dev / mean * 100.0 } else { 0.0 };
+        let margin = Self::critical_value(count) * std_dev / (count as f64).sqrt();
+
+        Self {
+            count,
+            mean,
+            median: Self::percentile(&sorted, 50.0),
+            std_dev,
+            coefficient_of_variation,
+            min: sorted[0],
+            max: sorted[count - 1],
+            p95: Self::percentile(&sorted, 95.0),
+            p99: Self::percentile(&sorted, 99.0),
+            confidence_interval: (mean - margin, mean + margin),
+        }
+    }
+
+    ///Percentile of already sorted values, linearly interpolated between the closest ranks
+    pub fn percentile(sorted: &[f64], percent: f64) -> f64 {
+        if sorted.is_empty() {
+            return 0.0;
+        }
+
+        let rank = percent / 100.0 * (sorted.len() - 1) as f64;
+        let lower = rank.floor() as usize;
+        let upper = rank.ceil() as usize;
+        sorted[lower] + (sorted[upper] - sorted[lower]) * (rank - lower as f64)
+    }
+
+    fn critical_value(count: usize) -> f64 {
+        match count {
+            0 | 1 => 0.0,
+            n if n - 1 <= T_95.len() => T_95[n - 2],
+            _ => Z_95,
+        }
+    }
+
+    ///Whether the 95% confidence intervals of both summaries overlap
+    pub fn overlaps(&self, other: &Statistics) -> bool {
+        self.confidence_interval.0 <= other.confidence_interval.1
+            && other.confidence_interval.0 <= self.confidence_interval.1
+    }
+}
+
+///Number of measurements falling below an upper bound
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HistogramBucket {
+    pub upper_bound: f64,
+    pub count: usize,
+}
+
+///Histogram with buckets doubling in width, starting at `first_bound`
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Histogram {
+    pub buckets: Vec<HistogramBucket>,
+}
+
+impl Histogram {
+    pub fn new(measurements: &[f64], first_bound: f64) -> Self {
+        let max = measurements.iter().copied().fold(0.0, f64::max);
+        let mut buckets = vec![HistogramBucket { upper_bound: first_bound, count: 0 }];
+        while buckets.last().unwrap().upper_bound < max {
+            let upper_bound = buckets.last().unwrap().upper_bound * 2.0;
+            buckets.push(HistogramBucket { upper_bound, count: 0 });
+        }
+
+        for measurement in measurements {
+            let bucket = buckets.iter_mut()
+                .find(|bucket| *measurement <= bucket.upper_bound)
+                .unwrap();
+            bucket.count += 1;
+        }
+
+        Self { buckets }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!((actual - expected).abs() < 1e-9, "expected {}, got {}", expected, actual);
+    }
+
+    #[test]
+    fn percentile_interpolates_between_ranks() {
+        let sorted = [10.0, 20.0, 30.0, 40.0, 50.0];
+        assert_close(Statistics::percentile(&sorted, 0.0), 10.0);
+        assert_close(Statistics::percentile(&sorted, 50.0), 30.0);
+        assert_close(Statistics::percentile(&sorted, 95.0), 48.0);
+        assert_close(Statistics::percentile(&sorted, 100.0), 50.0);
+        assert_close(Statistics::percentile(&[1.0, 2.0, 3.0, 4.0], 50.0), 2.5);
+        assert_close(Statistics::percentile(&[7.0], 99.0), 7.0);
+        assert_close(Statistics::percentile(&[], 50.0), 0.0);
+    }
+
+    #[test]
+    fn critical_value_uses_degrees_of_freedom() {
+        assert_close(Statistics::critical_value(0), 0.0);
+        assert_close(Statistics::critical_value(1), 0.0);
+        assert_close(Statistics::critical_value(2), 12.706);
+        assert_close(Statistics::critical_value(10), 2.262);
+        assert_close(Statistics::critical_value(31), 2.042);
+        assert_close(Statistics::critical_value(32), Z_95);
+        assert_close(Statistics::critical_value(1000), Z_95);
+    }
+
+    #[test]
+    fn statistics_of_known_sample() {
+        let statistics = Statistics::new(&[9.0, 2.0, 4.0, 4.0, 5.0, 5.0, 4.0, 7.0]);
+        let std_dev = (32.0f64 / 7.0).sqrt();
+        let margin = 2.365 * std_dev / 8.0f64.sqrt();
+
+        assert_eq!(statistics.count, 8);
+        assert_close(statistics.mean, 5.0);
+        assert_close(statistics.median, 4.5);
+        assert_close(statistics.std_dev, std_dev);
+        assert_close(statistics.coefficient_of_variation, std_dev / 5.0 * 100.0);
+        assert_close(statistics.min, 2.0);
+        assert_close(statistics.max, 9.0);
+        assert_close(statistics.confidence_interval.0, 5.0 - margin);
+        assert_close(statistics.confidence_interval.1, 5.0 + margin);
+    }
+
+    #[test]
+    fn statistics_of_single_and_no_measurement() {
+        let single = Statistics::new(&[3.0]);
+        assert_close(single.std_dev, 0.0);
+        assert_eq!(single.confidence_interval, (3.0, 3.0));
+        assert_eq!(Statistics::new(&[]), Statistics::default());
+    }
+
+    #[test]
+    fn histogram_buckets_double_in_width() {
+        let histogram = Histogram::new(&[0.5, 1.0, 3.0, 10.0], 1.0);
+        let bounds: Vec<f64> = histogram.buckets.iter().map(|bucket| bucket.upper_bound).collect();
+        let counts: Vec<usize> = histogram.buckets.iter().map(|bucket| bucket.count).collect();
+        assert_eq!(bounds, [1.0, 2.0, 4.0, 8.0, 16.0]);
+        assert_eq!(counts, [2, 0, 1, 0, 1]);
+    }
+
+    #[test]
+    fn histogram_of_no_measurement_has_one_empty_bucket() {
+        assert_eq!(Histogram::new(&[], 0.000_001).buckets, [HistogramBucket { upper_bound: 0.000_001, count: 0 }]);
+    }
+}
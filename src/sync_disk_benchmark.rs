@@ -0,0 +1,152 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+use clap::ValueEnum;
+use indicatif::HumanBytes;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use color_eyre::eyre::{Result, WrapErr};
+use serde_json::json;
+use crate::benchmark::{Benchmark, BenchmarkKind, Measurement};
+use crate::disk_benchmark::DiskBenchmark;
+use crate::interrupt::Interrupt;
+use crate::results::{Parameters, Unit};
+
+///How written records are made durable
+#[derive(ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncMethod {
+    ///`fdatasync`, flushes the data and only the metadata needed to read it back
+    Data,
+    ///`fsync`, flushes the data and all file metadata
+    All,
+}
+
+impl SyncMethod {
+    fn sync(&self, file: &File) -> io::Result<()> {
+        match self {
+            SyncMethod::Data => file.sync_data(),
+            SyncMethod::All => file.sync_all(),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            SyncMethod::Data => "fdatasync",
+            SyncMethod::All => "fsync",
+        }
+    }
+}
+
+///Appends small records to the benchmark file and flushes each one to stable storage,
+///the way a database commits transactions.
+pub struct SyncDiskBenchmark {
+    path: String,
+    record: Vec<u8>,
+    num_operations: u32,
+    record_size: usize,
+    sync_method: SyncMethod,
+    interrupt: Interrupt,
+}
+
+impl SyncDiskBenchmark {
+    pub fn new(path: String,
+               num_operations: u32,
+               record_size: u64,
+               sync_method: SyncMethod) -> Self {
+        Self {
+            path: DiskBenchmark::benchmark_file_path(&path, "sync.diskbenchmark"),
+            record: Vec::new(),
+            num_operations,
+            record_size: (record_size as usize).max(1),
+            sync_method,
+            interrupt: Interrupt::new(),
+        }
+    }
+
+    ///Stops the writes early once `interrupt` is set
+    pub fn with_interrupt(mut self, interrupt: Interrupt) -> Self {
+        self.interrupt = interrupt;
+        self
+    }
+
+    ///Writes and flushes every record of one iteration and returns the latency of every flush
+    fn write_records(&self) -> Result<Vec<Duration>> {
+        DiskBenchmark::delete_benchmark_file(&self.path);
+        let mut file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&self.path)
+            .wrap_err_with(|| format!("Unable to create the benchmark file {}", self.path))?;
+
+        let mut latencies = Vec::with_capacity(self.num_operations as usize);
+        for _ in 0..self.num_operations {
+            if self.interrupt.is_set() {
+                break;
+            }
+            let now = Instant::now();
+            file.write_all(&self.record)
+                .wrap_err_with(|| format!("Unable to write to the benchmark file {}", self.path))?;
+            self.sync_method.sync(&file)
+                .wrap_err_with(|| format!("Unable to flush the benchmark file {} with {}", self.path, self.sync_method.name()))?;
+            latencies.push(now.elapsed());
+        }
+
+        Ok(latencies)
+    }
+}
+
+impl Benchmark for SyncDiskBenchmark {
+    fn name(&self) -> String {
+        String::from("disk_sync")
+    }
+
+    fn category(&self) -> String {
+        BenchmarkKind::DiskSync.name()
+    }
+
+    fn parameters(&self) -> Parameters {
+        Parameters::from([
+            (String::from("record_size"), json!(self.record_size)),
+            (String::from("num_operations"), json!(self.num_operations)),
+            (String::from("sync_method"), json!(self.sync_method)),
+        ])
+    }
+
+    fn unit(&self) -> Unit {
+        Unit::FlushesPerSecond
+    }
+
+    fn description(&self) -> String {
+        format!("Writing {} records followed by {} reached",
+                HumanBytes(self.record_size as u64),
+                self.sync_method.name())
+    }
+
+    fn progress_message(&self) -> String {
+        format!("Writing {} records of size {} with {} to {}",
+                self.num_operations,
+                HumanBytes(self.record_size as u64),
+                self.sync_method.name(),
+                self.path)
+    }
+
+    fn setup(&mut self) -> Result<()> {
+        self.record = vec![0u8; self.record_size];
+        rand::rng().fill(&mut self.record[..]);
+        Ok(())
+    }
+
+    ///Only the flushes are timed, not opening and truncating the file
+    fn one_iteration(&mut self) -> Result<Measurement> {
+        let latencies = self.write_records()?;
+        let elapsed = latencies.iter().sum::<Duration>();
+        Ok(Measurement::throughput(latencies.len() as f64, elapsed).with_latencies(latencies))
+    }
+
+    fn teardown(&mut self) -> Result<()> {
+        DiskBenchmark::delete_benchmark_file(&self.path);
+        Ok(())
+    }
+}